@@ -0,0 +1,115 @@
+use anyhow::{Context, Result};
+use clap::{Parser, ValueEnum};
+use log::{error, info, warn};
+use std::fs::File;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+
+use quotesdirectlib::{
+    decoder::{MessageDecoder, ResetPolicy},
+    dynamic::AnyMessage,
+    sync::packets::TCPPacket,
+    templates::TemplateSet,
+};
+
+/// Dump FAST messages of a packets file (recorded SDS stream or quarantine file) as JSON lines.
+#[derive(Parser)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    /// File of TCP framed packets
+    #[arg(value_name = "FILE")]
+    file: PathBuf,
+    /// FAST templates file [default: bundled templates]
+    #[arg(short, long, value_name = "FILE")]
+    templates: Option<PathBuf>,
+    /// When the sender resets the FAST dictionaries
+    #[arg(short, long, value_enum, default_value_t = Reset::Never)]
+    reset: Reset,
+    /// Decode messages matching the typed structs with them, others dynamically
+    #[arg(long)]
+    typed: bool,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Reset {
+    /// UDP feed packets
+    PerPacket,
+    PerMessage,
+    /// SDS TCP stream
+    Never,
+}
+
+impl From<Reset> for ResetPolicy {
+    fn from(reset: Reset) -> Self {
+        match reset {
+            Reset::PerPacket => ResetPolicy::PerPacket,
+            Reset::PerMessage => ResetPolicy::PerMessage,
+            Reset::Never => ResetPolicy::Never,
+        }
+    }
+}
+
+fn main() -> Result<()> {
+    env_logger::init_from_env(
+        env_logger::Env::default()
+            .filter_or("LOG_LEVEL", "info")
+            .write_style_or("LOG_STYLE", "always"),
+    );
+
+    let args = Args::parse();
+    let templates = load_templates(args.templates.as_deref())?;
+    let mut decoder = MessageDecoder::with_templates(templates.xml(), args.reset.into())?;
+    let mut input = BufReader::new(File::open(&args.file)?);
+
+    let mut packets = 0;
+    let mut messages = 0;
+    while let Some(packet) = TCPPacket::read(&mut input)? {
+        packets += 1;
+        decoder.start_packet();
+        let mut payload = packet.payload.as_slice();
+        while !payload.is_empty() {
+            let result = if args.typed {
+                decoder.decode_any(payload)
+            } else {
+                decoder
+                    .decode_dynamic(payload)
+                    .map(|(msg, size)| (AnyMessage::Dynamic(msg), size))
+            };
+            match result {
+                Ok((msg, size)) => {
+                    println!("{}", serde_json::to_string(&msg)?);
+                    payload = &payload[size..];
+                }
+                Err(err) => {
+                    error!(
+                        "Failed to parse FAST message in packet seq_num={}: {err} ({} bytes discarded)",
+                        packet.seq_num,
+                        payload.len()
+                    );
+                    decoder.recover();
+                    break;
+                }
+            }
+            messages += 1;
+        }
+    }
+    info!(
+        "Packets: {packets}, messages: {messages}, decode errors: {}",
+        decoder.decode_errors()
+    );
+    Ok(())
+}
+
+/// Load templates without requiring them to match the typed messages,
+/// mismatching messages are dumped dynamically.
+fn load_templates(path: Option<&Path>) -> Result<TemplateSet> {
+    let Some(path) = path else {
+        return Ok(TemplateSet::bundled());
+    };
+    let templates = TemplateSet::from_file(path)
+        .with_context(|| format!("Failed to load templates file {}", path.display()))?;
+    for mismatch in templates.mismatches() {
+        warn!("Templates do not match the typed messages: {mismatch}");
+    }
+    Ok(templates)
+}
@@ -1,17 +1,24 @@
 use anyhow::Result;
 use clap::Parser;
-use log::{debug, error, info};
+use log::{debug, error, info, warn};
 use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
+use tokio_util::sync::CancellationToken;
 
-use fastlib::Decoder;
 use quotesdirectlib::{
-    fast::{Message, TEMPLATES_XML},
+    decoder::{MessageDecoder, ResetPolicy},
+    fast::Message,
     packets::UDPPacket,
+    recovery::RecoveryTracker,
+    sequence::{SequenceCheck, SequenceTracker},
+    templates::TemplateSet,
 };
 
 use examples::{
-    config::{FFSClientConfig, read_from_file},
-    network::make_multicast_udp_socket,
+    busy_poll::{BusyPollReceiver, Received},
+    config::{FFSClientConfig, ReceiveMode, load_templates, read_from_file},
+    network::{DropCounter, MAX_DATAGRAM_SIZE, make_multicast_udp_socket},
+    quarantine::Quarantine,
     setup_ctrl_c_handler,
 };
 
@@ -49,21 +56,32 @@ async fn main() -> Result<()> {
     }
 }
 
-const MAX_DATAGRAM_SIZE: usize = 65507;
-
 async fn run(cfg: FFSClientConfig) -> Result<()> {
     info!("Configuration: {cfg:#?}");
 
+    if cfg.mode == ReceiveMode::BusyPoll {
+        return run_busy_poll(cfg).await;
+    }
+
+    #[cfg(target_os = "linux")]
+    if let Some(batch_size) = cfg.batch_size {
+        return run_batched(cfg, batch_size).await;
+    }
+
     let socket = make_multicast_udp_socket(
         &cfg.connection.mcast_group,
         cfg.connection.mcast_port,
-        &cfg.interface,
+        &cfg.connection.mcast_source,
+        &cfg.interfaces,
         &cfg.rcvbuf,
     )
     .await?;
+    let drops = DropCounter::new(&socket)?;
 
-    let mut decoder = Decoder::new_from_xml(TEMPLATES_XML)?;
+    let templates = load_templates(cfg.templates_file.as_deref())?;
+    let mut feed = Feed::new(&templates, quarantine(&cfg)?)?;
     let token = setup_ctrl_c_handler();
+    let mut stats_timer = stats_interval(&cfg);
 
     let mut buffer = vec![0u8; MAX_DATAGRAM_SIZE].into_boxed_slice();
     'main: loop {
@@ -73,31 +91,308 @@ async fn run(cfg: FFSClientConfig) -> Result<()> {
                 debug!("Got cancellation signal");
                 break 'main
             },
+            _ = stats_timer.tick() => {
+                feed.report_stats(drops.read().ok().flatten());
+                continue 'main
+            },
             result = socket.recv(&mut buffer) => {
                 let n = result?;
                 &buffer[..n]
             }
         };
+        feed.process_datagram(raw, None);
+    }
+    feed.report_stats(drops.read().ok().flatten());
+    info!("Exiting...");
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+async fn run_batched(cfg: FFSClientConfig, batch_size: usize) -> Result<()> {
+    use examples::network::make_multicast_batch_socket;
+
+    let mut socket = make_multicast_batch_socket(
+        &cfg.connection.mcast_group,
+        cfg.connection.mcast_port,
+        &cfg.connection.mcast_source,
+        &cfg.interfaces,
+        &cfg.rcvbuf,
+        batch_size,
+    )
+    .await?;
+
+    let templates = load_templates(cfg.templates_file.as_deref())?;
+    let mut feed = Feed::new(&templates, quarantine(&cfg)?)?;
+    let token = setup_ctrl_c_handler();
+    let mut stats_timer = stats_interval(&cfg);
+    let mut drops = 0;
+
+    'main: loop {
+        // Read batch of datagrams from socket
+        let datagrams = tokio::select! {
+            () = token.cancelled() => {
+                debug!("Got cancellation signal");
+                break 'main
+            },
+            _ = stats_timer.tick() => {
+                feed.report_stats(Some(u64::from(drops)));
+                continue 'main
+            },
+            result = socket.recv() => result?,
+        };
+        for datagram in datagrams {
+            feed.process_datagram(datagram.data, datagram.timestamp);
+        }
+        drops = socket.drops();
+    }
+    feed.report_stats(Some(u64::from(socket.drops())));
+    info!("Exiting...");
+    Ok(())
+}
+
+async fn run_busy_poll(cfg: FFSClientConfig) -> Result<()> {
+    let socket = make_multicast_udp_socket(
+        &cfg.connection.mcast_group,
+        cfg.connection.mcast_port,
+        &cfg.connection.mcast_source,
+        &cfg.interfaces,
+        &cfg.rcvbuf,
+    )
+    .await?;
+    let drops = DropCounter::new(&socket)?;
+
+    // the receive thread decodes and quarantines packets
+    let templates = load_templates(cfg.templates_file.as_deref())?;
+    let mut receiver = BusyPollReceiver::spawn(
+        vec![socket.into_std()?],
+        cfg.cpu,
+        cfg.ring_capacity,
+        templates.clone(),
+        quarantine(&cfg)?,
+    )?;
+    let token = setup_ctrl_c_handler();
+    let stats_interval = Duration::from_secs(cfg.stats_interval_sec.max(1));
+
+    // the decoder is not `Send`, so the consumer thread creates the feed itself
+    tokio::task::spawn_blocking(move || -> Result<()> {
+        let mut feed = Feed::new(&templates, None)?;
+        consume(&mut receiver, &mut feed, &token, stats_interval, drops);
+        feed.report_stats(drops.read().ok().flatten());
+        Ok(())
+    })
+    .await??;
+    info!("Exiting...");
+    Ok(())
+}
 
+/// Consume the ring buffer on a blocking thread until cancelled
+/// and the receive thread's messages are drained.
+fn consume(
+    receiver: &mut BusyPollReceiver,
+    feed: &mut Feed,
+    token: &CancellationToken,
+    stats_interval: Duration,
+    drops: DropCounter,
+) {
+    const SPINS: u32 = 10_000;
+    const IDLE_SLEEP: Duration = Duration::from_micros(50);
+
+    let mut last_stats = SystemTime::now();
+    let mut last_seq_num = None;
+    let mut idle = 0;
+    loop {
+        if token.is_cancelled() {
+            receiver.stop();
+        }
+        if last_stats.elapsed().unwrap_or_default() >= stats_interval {
+            feed.report_stats(drops.read().ok().flatten());
+            last_stats = SystemTime::now();
+        }
+        let Some(msg) = receiver.pop() else {
+            if receiver.is_finished() {
+                break;
+            }
+            // spin shortly for latency, then back off
+            idle += 1;
+            if idle < SPINS {
+                std::hint::spin_loop();
+            } else {
+                std::thread::sleep(IDLE_SLEEP);
+            }
+            continue;
+        };
+        idle = 0;
+
+        if let Received::Overflow(lost) = msg.message {
+            feed.local_loss(lost);
+            last_seq_num = None;
+            continue;
+        }
+        // messages of a packet share its sequence number
+        if last_seq_num != Some(msg.seq_num) {
+            last_seq_num = Some(msg.seq_num);
+            feed.check_sequence(msg.seq_num);
+        }
+        if let Ok(latency) = msg.timestamp.elapsed() {
+            debug!("Packet seq_num={} consumed in {latency:?}", msg.seq_num);
+        }
+        match &msg.message {
+            Received::Message(message) => feed.process_message(message),
+            Received::DecodeError => feed.recovery.mark_all(),
+            Received::Overflow(_) => {}
+        }
+    }
+    // losses at the very end have no entry to be reported before
+    let unreported = receiver.overflows() - feed.ring_overflows;
+    if unreported > 0 {
+        feed.local_loss(unreported);
+    }
+}
+
+fn stats_interval(cfg: &FFSClientConfig) -> tokio::time::Interval {
+    let period = Duration::from_secs(cfg.stats_interval_sec.max(1));
+    tokio::time::interval_at(tokio::time::Instant::now() + period, period)
+}
+
+fn quarantine(cfg: &FFSClientConfig) -> Result<Option<Quarantine>> {
+    cfg.quarantine_file
+        .as_deref()
+        .map(Quarantine::create)
+        .transpose()
+}
+
+struct Feed {
+    decoder: MessageDecoder,
+    sequence: SequenceTracker,
+    messages: SequenceTracker,
+    recovery: RecoveryTracker,
+    quarantine: Option<Quarantine>,
+    /// Messages lost in the busy-poll ring buffer.
+    ring_overflows: u64,
+}
+
+impl Feed {
+    fn new(templates: &TemplateSet, quarantine: Option<Quarantine>) -> Result<Self> {
+        Ok(Self {
+            // UDP packets are self-contained
+            decoder: MessageDecoder::with_templates(templates.xml(), ResetPolicy::PerPacket)?,
+            sequence: SequenceTracker::new(),
+            messages: SequenceTracker::new(),
+            recovery: RecoveryTracker::new(),
+            quarantine,
+            ring_overflows: 0,
+        })
+    }
+
+    fn process_datagram(&mut self, raw: &[u8], timestamp: Option<SystemTime>) {
         // Parse UDP packet
         let packet = match UDPPacket::read(raw) {
             Ok(pkt) => pkt,
             Err(err) => {
                 error!("Failed to parse UDP packet: {err}");
-                continue;
+                return;
             }
         };
+        self.check_sequence(packet.seq_num);
 
-        // Parse FAST message
-        let message: Message = match fastlib::from_slice(&mut decoder, packet.payload) {
-            Ok(msg) => msg,
-            Err(err) => {
-                error!("Failed to parse FAST message: {err}");
-                continue;
+        // Parse FAST messages, the rest of the packet is discarded on error
+        let decoded = self.decoder.decode_packet_recovering(packet.payload);
+        if let Some(latency) = timestamp.and_then(|ts| ts.elapsed().ok()) {
+            debug!("Packet seq_num={} decoded in {latency:?}", packet.seq_num);
+        }
+        for message in &decoded.messages {
+            self.process_message(message);
+        }
+        if let Some(err) = decoded.error {
+            error!(
+                "Failed to parse FAST message in packet seq_num={}: {err} ({} bytes discarded)",
+                packet.seq_num, decoded.discarded
+            );
+            self.recovery.mark_all();
+            if let Some(quarantine) = &mut self.quarantine
+                && let Err(err) =
+                    quarantine.write(packet.seq_num, packet.sub_channel, packet.payload)
+            {
+                error!("Failed to quarantine packet: {err}");
             }
-        };
+        }
+    }
+
+    fn process_message(&mut self, message: &Message) {
+        self.check_message(message);
+        if let Message::MDSnapshotFullRefresh(snapshot) = message
+            && self.recovery.needs_recovery(snapshot.security_id)
+        {
+            info!("security_id={} recovered", snapshot.security_id);
+        }
+        self.recovery.process(message);
         info!("{message:#?}");
     }
-    info!("Exiting...");
-    Ok(())
+
+    /// Messages were lost locally: instruments need recovery, and the following sequence gaps
+    /// are not network losses.
+    fn local_loss(&mut self, lost: u64) {
+        self.ring_overflows += lost;
+        error!("Ring buffer overflow: {lost} messages lost locally");
+        self.recovery.mark_all();
+        self.sequence.resync();
+        self.messages.resync();
+    }
+
+    fn check_sequence(&mut self, seq_num: u32) {
+        match self.sequence.check(seq_num) {
+            // packet numbers are not reset, see `check_message`
+            SequenceCheck::InOrder | SequenceCheck::Reset { .. } => {}
+            SequenceCheck::Gap {
+                expected, missed, ..
+            } => {
+                error!("expected packet seq_num={expected} but got={seq_num} ({missed} missed)");
+            }
+            SequenceCheck::Duplicate { expected, .. } => {
+                error!("expected packet seq_num={expected} but got={seq_num}");
+            }
+            SequenceCheck::Restart { expected, .. } => {
+                warn!("sender restarted at packet seq_num={seq_num}, expected={expected}");
+            }
+        }
+    }
+
+    fn check_message(&mut self, message: &Message) {
+        match self.messages.check_message(message) {
+            SequenceCheck::InOrder => {}
+            SequenceCheck::Gap {
+                expected, received, ..
+            }
+            | SequenceCheck::Duplicate { expected, received } => {
+                debug!("expected msg seq_num={expected} but got={received}");
+            }
+            SequenceCheck::Reset {
+                expected,
+                new_seq_no,
+            } => {
+                warn!("sequence reset from msg seq_num={expected:?} to {new_seq_no}");
+            }
+            SequenceCheck::Restart { expected, received } => {
+                warn!("sender restarted at msg seq_num={received}, expected={expected}");
+            }
+        }
+    }
+
+    /// Report sequence gaps alongside socket drops, so gaps caused by local buffer overruns can be told apart.
+    fn report_stats(&self, drops: Option<u64>) {
+        let stats = self.sequence.stats();
+        let drops = drops.map_or_else(|| "n/a".to_string(), |d| d.to_string());
+        info!(
+            "Stats: packets={} gaps={} missed={} duplicates={} restarts={} socket drops={drops} ring overflows={} resets={} decode errors={} needing recovery={}",
+            stats.received,
+            stats.gaps,
+            stats.missed,
+            stats.duplicates,
+            stats.restarts,
+            self.ring_overflows,
+            self.messages.stats().resets,
+            self.recovery.events(),
+            self.recovery.stale().count()
+        );
+    }
 }
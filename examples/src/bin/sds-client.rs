@@ -1,15 +1,21 @@
 use anyhow::Result;
 use clap::Parser;
 use humantime::format_duration;
-use log::{debug, error, info};
+use log::{debug, error, info, warn};
 use std::path::PathBuf;
 use std::time::{Duration, SystemTime};
 
-use quotesdirectlib::fast::{Message, SecurityDefinition};
+use quotesdirectlib::fast::{
+    Logon, Message, News, SecurityDefinition, SecurityDefinitionRequest, SecurityStatus,
+    SequenceReset, UserNotification,
+};
+use quotesdirectlib::handler::{MessageHandler, UserStatus};
 
 use examples::{
     client::{Feeds, SDSClient},
-    config::{SDSClientConfig, read_from_file},
+    config::{SDSClientConfig, load_templates, read_from_file},
+    network::Resolver,
+    quarantine::Quarantine,
     setup_ctrl_c_handler,
 };
 
@@ -58,9 +64,17 @@ async fn run(cfg: SDSClientConfig) -> Result<()> {
 
     let feeds = Feeds::from_str(&cfg.feeds)?;
 
-    let mut sds = SDSClient::new();
-
     let s = &cfg.sds;
+    let mut sds = SDSClient::with_resolver(
+        Resolver::new(cfg.hosts.clone()),
+        Duration::from_secs(s.connect_timeout_sec),
+    );
+    if cfg.templates_file.is_some() {
+        sds.set_templates(&load_templates(cfg.templates_file.as_deref())?)?;
+    }
+    if let Some(path) = &cfg.quarantine_file {
+        sds.set_quarantine(Quarantine::create(path)?);
+    }
     sds.connect(&s.host, s.port, &s.login, &s.password).await?;
     info!("Subscribing to feeds...");
     sds.subscribe_feeds(feeds).await?;
@@ -127,24 +141,65 @@ async fn run(cfg: SDSClientConfig) -> Result<()> {
                 }
             }
             Message::MDHeartbeat(_) => {}
-            Message::MDLogon(m) => {
-                debug!("{m:?}");
-            }
             Message::MDLogout(m) => {
                 info!("Got logout message");
                 debug!("{m:?}");
                 break 'main;
             }
-            Message::MDSecurityDefinitionRequest(m) => {
+            Message::ApplicationMessageRequestAck(m) => {
                 debug!("{m:?}");
+                let entitlements = sds.entitlements();
+                if entitlements.is_complete() {
+                    for feed in entitlements.iter() {
+                        if feed.is_granted() {
+                            info!("Feed {}: {}", feed.appl_id, feed.status);
+                        } else {
+                            warn!("Feed {}: {}", feed.appl_id, feed.status);
+                        }
+                    }
+                }
             }
-            _ => unreachable!(),
+            other => Logger.on_message(&other),
         }
     }
     info!("Exiting...");
     Ok(())
 }
 
+/// Logs messages the loader does not act on.
+struct Logger;
+
+impl MessageHandler for Logger {
+    fn on_logon(&mut self, msg: &Logon) {
+        debug!("{msg:?}");
+    }
+
+    fn on_security_definition_request(&mut self, msg: &SecurityDefinitionRequest) {
+        debug!("{msg:?}");
+    }
+
+    fn on_sequence_reset(&mut self, msg: &SequenceReset) {
+        debug!("{msg:?}");
+    }
+
+    fn on_security_status(&mut self, msg: &SecurityStatus) {
+        debug!("{msg:?}");
+    }
+
+    fn on_news(&mut self, msg: &News) {
+        debug!("{msg:?}");
+    }
+
+    fn on_user_notification(&mut self, msg: &UserNotification) {
+        match msg.status() {
+            UserStatus::LoggedIn | UserStatus::PasswordChanged => {
+                info!("User notification: {}", msg.text);
+            }
+            status => warn!("User notification ({status:?}): {}", msg.text),
+        }
+    }
+}
+
 fn security_definition_as_string(sd: &SecurityDefinition) -> String {
     let symbol = match &sd.cqg_security_name {
         Some(symbol) => symbol.as_str(),
@@ -0,0 +1,272 @@
+//! Dedicated busy-poll receive thread.
+//!
+//! The thread spins over non-blocking multicast sockets, decodes FAST messages with its own decoder
+//! and hands them to the consumer through a lock-free SPSC ring buffer.
+//! Packets that fail to decode are counted and optionally written to a quarantine file;
+//! the error is passed through the ring buffer after the messages decoded before it.
+//! Messages dropped while the ring buffer is full are reported in-band too, once there is room.
+//!
+use anyhow::{Context, Result};
+use log::{error, info, warn};
+use std::io::ErrorKind;
+use std::net::UdpSocket;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::thread::JoinHandle;
+use std::time::SystemTime;
+
+use quotesdirectlib::{
+    decoder::{MessageDecoder, ResetPolicy},
+    fast::Message,
+    packets::UDPPacket,
+    templates::TemplateSet,
+};
+
+use crate::network::MAX_DATAGRAM_SIZE;
+use crate::quarantine::Quarantine;
+
+/// Message decoded by the receive thread.
+#[derive(Debug)]
+pub struct ReceivedMessage {
+    pub seq_num: u32,
+    /// Time the datagram was read from the socket.
+    pub timestamp: SystemTime,
+    pub message: Received,
+}
+
+/// Entry of the ring buffer.
+#[allow(clippy::large_enum_variant)]
+#[derive(Debug)]
+pub enum Received {
+    Message(Message),
+    /// The rest of the packet failed to decode; messages may have been lost.
+    DecodeError,
+    /// Entries dropped because the ring buffer was full, before this one.
+    Overflow(u64),
+}
+
+/// Handle to the busy-poll receive thread; it is the consumer side of the ring buffer.
+pub struct BusyPollReceiver {
+    consumer: rtrb::Consumer<ReceivedMessage>,
+    stop: Arc<AtomicBool>,
+    overflows: Arc<AtomicU64>,
+    decode_errors: Arc<AtomicU64>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl BusyPollReceiver {
+    /// Spawn the receive thread, optionally pinned to `cpu`.
+    /// The sockets are switched to non-blocking mode.
+    /// # Errors
+    /// Returns an error if `cpu` is out of range, or if failed to setup a socket or to spawn the thread.
+    pub fn spawn(
+        sockets: Vec<UdpSocket>,
+        cpu: Option<usize>,
+        capacity: usize,
+        templates: TemplateSet,
+        quarantine: Option<Quarantine>,
+    ) -> Result<Self> {
+        if let Some(cpu) = cpu {
+            check_cpu(cpu)?;
+        }
+        for socket in &sockets {
+            socket
+                .set_nonblocking(true)
+                .context("socket.set_nonblocking()")?;
+        }
+        let (producer, consumer) = rtrb::RingBuffer::new(capacity);
+        let stop = Arc::new(AtomicBool::new(false));
+        let overflows = Arc::new(AtomicU64::new(0));
+        let decode_errors = Arc::new(AtomicU64::new(0));
+
+        let thread = std::thread::Builder::new()
+            .name("busy-poll".to_string())
+            .spawn({
+                let stop = stop.clone();
+                let overflows = overflows.clone();
+                let decode_errors = decode_errors.clone();
+                move || {
+                    if let Some(cpu) = cpu {
+                        pin_to_cpu(cpu);
+                    }
+                    // decoder is not `Send`, so the thread makes its own
+                    match MessageDecoder::with_templates(templates.xml(), ResetPolicy::PerPacket) {
+                        Ok(decoder) => poll_sockets(
+                            &sockets,
+                            decoder,
+                            producer,
+                            quarantine,
+                            &stop,
+                            &overflows,
+                            &decode_errors,
+                        ),
+                        Err(err) => error!("Failed to create decoder: {err}"),
+                    }
+                }
+            })
+            .context("thread spawn")?;
+
+        Ok(Self {
+            consumer,
+            stop,
+            overflows,
+            decode_errors,
+            thread: Some(thread),
+        })
+    }
+
+    /// Take the next decoded message, if any.
+    pub fn pop(&mut self) -> Option<ReceivedMessage> {
+        self.consumer.pop().ok()
+    }
+
+    /// Number of messages dropped because the ring buffer was full.
+    pub fn overflows(&self) -> u64 {
+        self.overflows.load(Ordering::Relaxed)
+    }
+
+    /// Number of packets that failed to decode.
+    pub fn decode_errors(&self) -> u64 {
+        self.decode_errors.load(Ordering::Relaxed)
+    }
+
+    /// Returns `true` if the receive thread has exited.
+    pub fn is_finished(&self) -> bool {
+        self.thread.as_ref().is_none_or(JoinHandle::is_finished)
+    }
+
+    /// Stop the receive thread and wait for it to exit.
+    pub fn stop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take()
+            && thread.join().is_err()
+        {
+            error!("Busy-poll thread panicked");
+        }
+    }
+}
+
+impl Drop for BusyPollReceiver {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+fn poll_sockets(
+    sockets: &[UdpSocket],
+    mut decoder: MessageDecoder,
+    mut producer: rtrb::Producer<ReceivedMessage>,
+    mut quarantine: Option<Quarantine>,
+    stop: &AtomicBool,
+    overflows: &AtomicU64,
+    decode_errors: &AtomicU64,
+) {
+    info!("Busy-poll thread started");
+    let mut buffer = vec![0u8; MAX_DATAGRAM_SIZE].into_boxed_slice();
+    // entries dropped since the last overflow report
+    let mut lost = 0;
+    while !stop.load(Ordering::Relaxed) {
+        let mut idle = true;
+        for socket in sockets {
+            let n = match socket.recv(&mut buffer) {
+                Ok(n) => n,
+                Err(err) if err.kind() == ErrorKind::WouldBlock => continue,
+                Err(err) => {
+                    error!("Failed to receive datagram: {err}");
+                    return;
+                }
+            };
+            idle = false;
+            let timestamp = SystemTime::now();
+
+            // Parse UDP packet
+            let packet = match UDPPacket::read(&buffer[..n]) {
+                Ok(pkt) => pkt,
+                Err(err) => {
+                    error!("Failed to parse UDP packet: {err}");
+                    continue;
+                }
+            };
+
+            // Parse FAST messages, the rest of the packet is discarded on error
+            let result = decoder.decode_packet_recovering(packet.payload);
+            if let Some(err) = &result.error {
+                decode_errors.fetch_add(1, Ordering::Relaxed);
+                error!(
+                    "Failed to parse FAST message in packet seq_num={}: {err} ({} bytes discarded)",
+                    packet.seq_num, result.discarded
+                );
+                if let Some(quarantine) = &mut quarantine
+                    && let Err(err) =
+                        quarantine.write(packet.seq_num, packet.sub_channel, packet.payload)
+                {
+                    error!("Failed to quarantine packet: {err}");
+                }
+            }
+
+            // the error follows the messages decoded before it
+            let error = result.error.is_some().then_some(Received::DecodeError);
+            for message in result
+                .messages
+                .into_iter()
+                .map(Received::Message)
+                .chain(error)
+            {
+                let entry = |message| ReceivedMessage {
+                    seq_num: packet.seq_num,
+                    timestamp,
+                    message,
+                };
+                if lost > 0 && producer.push(entry(Received::Overflow(lost))).is_ok() {
+                    lost = 0;
+                }
+                if lost > 0 || producer.push(entry(message)).is_err() {
+                    lost += 1;
+                    overflows.fetch_add(1, Ordering::Relaxed);
+                }
+            }
+        }
+        if idle {
+            std::hint::spin_loop();
+        }
+    }
+    info!("Busy-poll thread stopped");
+}
+
+/// `CPU_SET` panics on CPUs beyond the `cpu_set_t` size, so reject them before spawning the thread.
+#[cfg(target_os = "linux")]
+fn check_cpu(cpu: usize) -> Result<()> {
+    let setsize = usize::try_from(libc::CPU_SETSIZE).unwrap_or_default();
+    anyhow::ensure!(
+        cpu < setsize,
+        "CPU {cpu} is out of range, must be less than {setsize}"
+    );
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn check_cpu(_cpu: usize) -> Result<()> {
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+fn pin_to_cpu(cpu: usize) {
+    let res = unsafe {
+        let mut set: libc::cpu_set_t = std::mem::zeroed();
+        libc::CPU_SET(cpu, &mut set);
+        libc::sched_setaffinity(0, size_of::<libc::cpu_set_t>(), &raw const set)
+    };
+    if res == 0 {
+        info!("Busy-poll thread pinned to CPU {cpu}");
+    } else {
+        warn!(
+            "Failed to pin busy-poll thread to CPU {cpu}: {}",
+            std::io::Error::last_os_error()
+        );
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn pin_to_cpu(cpu: usize) {
+    warn!("Pinning busy-poll thread to CPU {cpu} is not supported on this platform");
+}
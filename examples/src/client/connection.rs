@@ -1,16 +1,21 @@
-use anyhow::{Result, bail};
-use bytes::Bytes;
-use fastlib::Decoder;
-use log::{debug, error};
+use anyhow::{Result, anyhow, bail};
+use bytes::{Buf, Bytes};
+use log::{debug, error, warn};
 use std::path::Path;
+use std::time::Duration;
 use tokio::fs::File;
 use tokio::io::{AsyncRead, AsyncWriteExt, BufReader, BufStream};
 use tokio::net::TcpStream;
 
+use crate::network::Resolver;
+use crate::quarantine::Quarantine;
 use quotesdirectlib::{
-    fast::{Message, TEMPLATES_XML},
+    decoder::{MessageDecoder, ResetPolicy},
+    fast::Message,
     fix::{login, logout, request},
     packets::TCPPacket,
+    sequence::{SequenceCheck, SequenceStats, SequenceTracker},
+    templates::TemplateSet,
 };
 
 enum DataSource {
@@ -20,11 +25,14 @@ enum DataSource {
 
 pub struct SDSConnection {
     source: Option<DataSource>,
-    decoder: Decoder,
-    in_seq_num_pkt: u32,
-    in_seq_num_msg: u32,
+    decoder: MessageDecoder,
+    in_seq_pkt: SequenceTracker,
+    in_seq_msg: SequenceTracker,
     out_seq_num: u32,
+    /// Sequence number, sub-channel and payload of the current packet.
+    packet: (u32, u8, Bytes),
     buff: Bytes,
+    quarantine: Option<Quarantine>,
 }
 
 impl SDSConnection {
@@ -32,17 +40,37 @@ impl SDSConnection {
         debug!("Creating new SDSConnection");
         Self {
             source: None,
-            decoder: Decoder::new_from_xml(TEMPLATES_XML).unwrap(),
-            in_seq_num_pkt: 1,
-            in_seq_num_msg: 1,
+            // TCP stream keeps the FAST state for the whole session
+            decoder: MessageDecoder::new(ResetPolicy::Never).unwrap(),
+            in_seq_pkt: SequenceTracker::starting_at(1),
+            in_seq_msg: SequenceTracker::starting_at(1),
             out_seq_num: 1,
+            packet: (0, 0, Bytes::new()),
             buff: Bytes::new(),
+            quarantine: None,
         }
     }
 
-    pub async fn connect(&mut self, host: &str, port: u16) -> Result<()> {
+    /// Decode messages with the templates instead of the bundled ones.
+    pub fn set_templates(&mut self, templates: &TemplateSet) -> Result<()> {
+        self.decoder = MessageDecoder::with_templates(templates.xml(), ResetPolicy::Never)?;
+        Ok(())
+    }
+
+    /// Dump packets that fail to decode to the quarantine file.
+    pub fn set_quarantine(&mut self, quarantine: Quarantine) {
+        self.quarantine = Some(quarantine);
+    }
+
+    pub async fn connect(
+        &mut self,
+        resolver: &Resolver,
+        host: &str,
+        port: u16,
+        timeout: Duration,
+    ) -> Result<()> {
         debug!("Connecting to {host}:{port}");
-        let stream = TcpStream::connect(format!("{host}:{port}")).await?;
+        let (stream, _) = resolver.connect(host, port, timeout).await?;
         self.source = Some(DataSource::Tcp(BufStream::new(stream)));
         Ok(())
     }
@@ -84,6 +112,11 @@ impl SDSConnection {
         Ok(())
     }
 
+    /// Read the next message.
+    /// # Errors
+    /// The FAST state is kept across the whole TCP stream, so the receiver cannot resynchronise
+    /// with the sender after a decode error: the packet is quarantined and an error is returned,
+    /// the session must be restarted.
     pub async fn read_message(&mut self) -> Result<Option<Message>> {
         if self.buff.is_empty() {
             // read next packet
@@ -97,45 +130,77 @@ impl SDSConnection {
             };
 
             // check packet's sequence number
-            if packet.seq_num != self.in_seq_num_pkt {
-                error!(
-                    "expected packet seq_num={} but got={}",
-                    self.in_seq_num_pkt, packet.seq_num
-                );
-                self.in_seq_num_pkt = packet.seq_num + 1; // reset sequence number
+            match self.in_seq_pkt.check(packet.seq_num) {
+                SequenceCheck::InOrder | SequenceCheck::Reset { .. } => {}
+                SequenceCheck::Gap { expected, .. } | SequenceCheck::Duplicate { expected, .. } => {
+                    error!(
+                        "expected packet seq_num={expected} but got={}",
+                        packet.seq_num
+                    );
+                }
+                SequenceCheck::Restart { expected, received } => {
+                    warn!("sender restarted at packet seq_num={received}, expected={expected}");
+                }
             }
-            self.in_seq_num_pkt += 1;
 
             // fill-in internal buffer
-            self.buff = bytes::Bytes::from(packet.payload);
+            let payload = Bytes::from(packet.payload);
+            self.buff = payload.clone();
+            self.packet = (packet.seq_num, packet.sub_channel, payload);
+            self.decoder.start_packet();
         }
 
         // decode message
-        let msg: Message = fastlib::from_bytes(&mut self.decoder, &mut self.buff)?;
+        let msg = match self.decoder.decode(&self.buff) {
+            Ok((msg, size)) => {
+                self.buff.advance(size);
+                msg
+            }
+            Err(err) => return Err(self.desynchronised(&err)),
+        };
 
         // check message's sequence number
-        let seq_num = match &msg {
-            Message::MDSecurityDefinition(m) => m.msg_header.msg_seq_num,
-            Message::MDHeartbeat(m) => m.msg_header.msg_seq_num,
-            Message::MDLogon(m) => m.msg_header.msg_seq_num,
-            Message::MDLogout(m) => m.msg_header.msg_seq_num,
-            Message::MDSecurityDefinitionRequest(m) => m.msg_header.msg_seq_num,
-            _ => {
-                bail!("unexpected SDS message: {msg:#?}");
+        match self.in_seq_msg.check_message(&msg) {
+            SequenceCheck::InOrder => {}
+            SequenceCheck::Gap {
+                expected, received, ..
+            }
+            | SequenceCheck::Duplicate { expected, received } => {
+                error!("expected msg seq_num={expected} but got={received}");
+            }
+            SequenceCheck::Reset {
+                expected,
+                new_seq_no,
+            } => {
+                warn!("sequence reset from msg seq_num={expected:?} to {new_seq_no}");
+            }
+            SequenceCheck::Restart { expected, received } => {
+                warn!("sender restarted at msg seq_num={received}, expected={expected}");
             }
-        };
-        if seq_num != self.in_seq_num_msg {
-            error!(
-                "expected msg seq_num={} but got={}",
-                self.in_seq_num_msg, seq_num
-            );
-            self.in_seq_num_msg = seq_num + 1; // reset sequence number
         }
-        self.in_seq_num_msg += 1;
 
         Ok(Some(msg))
     }
 
+    /// Quarantine the packet that failed to decode, the stream cannot be followed any further.
+    fn desynchronised(&mut self, err: &quotesdirectlib::Error) -> anyhow::Error {
+        let (seq_num, sub_channel, payload) = &self.packet;
+        self.buff.clear();
+        if let Some(quarantine) = &mut self.quarantine
+            && let Err(err) = quarantine.write(*seq_num, *sub_channel, payload)
+        {
+            error!("Failed to quarantine packet: {err}");
+        }
+        anyhow!(
+            "Failed to parse FAST message in packet seq_num={seq_num}, the session must be restarted: {err}"
+        )
+    }
+
+    /// Statistics of the incoming messages' sequence numbers.
+    pub fn message_stats(&self) -> &SequenceStats {
+        self.in_seq_msg.stats()
+    }
+
     pub fn reset(&mut self) {
         self.decoder.reset();
     }
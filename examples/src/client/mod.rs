@@ -1,16 +1,22 @@
 use anyhow::Result;
 use std::collections::HashSet;
+use std::time::Duration;
 
+use quotesdirectlib::entitlements::EntitlementReport;
 use quotesdirectlib::fast::Message;
+use quotesdirectlib::templates::TemplateSet;
 
 use self::connection::SDSConnection;
 pub use self::feeds::Feeds;
+use crate::network::Resolver;
+use crate::quarantine::Quarantine;
 
 #[allow(dead_code)]
 pub(crate) mod connection;
 pub(crate) mod feeds;
 
 const SEC_IDS_CAPACITY: usize = 1_500_000;
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
 
 pub struct SDSClient {
     pub defs_count_total: u32,
@@ -18,14 +24,27 @@ pub struct SDSClient {
 
     sds: SDSConnection,
     sec_ids: HashSet<(u32, u32)>,
+    entitlements: EntitlementReport,
+    resolver: Resolver,
+    connect_timeout: Duration,
 }
 
 impl SDSClient {
     #[must_use]
     pub fn new() -> Self {
+        Self::with_resolver(Resolver::default(), CONNECT_TIMEOUT)
+    }
+
+    /// Create a client that resolves the server host with `resolver` and
+    /// tries each server address for `connect_timeout`.
+    #[must_use]
+    pub fn with_resolver(resolver: Resolver, connect_timeout: Duration) -> Self {
         Self {
             sds: SDSConnection::new(),
             sec_ids: HashSet::with_capacity(SEC_IDS_CAPACITY),
+            entitlements: EntitlementReport::new(),
+            resolver,
+            connect_timeout,
             defs_count_total: 0,
             defs_count: 0,
         }
@@ -40,7 +59,9 @@ impl SDSClient {
         user: &str,
         password: &str,
     ) -> Result<()> {
-        self.sds.connect(host, port).await?;
+        self.sds
+            .connect(&self.resolver, host, port, self.connect_timeout)
+            .await?;
         self.sds.login(user, password).await?;
         Ok(())
     }
@@ -49,6 +70,7 @@ impl SDSClient {
     /// Returns an error if failed to send subscribe message.
     #[inline]
     pub async fn subscribe(&mut self, feed_id: u32) -> Result<()> {
+        self.entitlements.request(&feed_id.to_string());
         self.sds.request(feed_id).await
     }
 
@@ -80,11 +102,11 @@ impl SDSClient {
                 }
                 Ok(Some((message, is_update)))
             }
-            Message::MDHeartbeat(_)
-            | Message::MDLogon(_)
-            | Message::MDLogout(_)
-            | Message::MDSecurityDefinitionRequest(_) => Ok(Some((message, false))),
-            _ => unreachable!(),
+            Message::ApplicationMessageRequestAck(m) => {
+                self.entitlements.add_ack(m);
+                Ok(Some((message, false)))
+            }
+            _ => Ok(Some((message, false))),
         }
     }
 
@@ -94,6 +116,24 @@ impl SDSClient {
         self.sds.logout().await
     }
 
+    /// Decode messages with the templates instead of the bundled ones.
+    /// # Errors
+    /// Returns an error if failed to create the decoder.
+    pub fn set_templates(&mut self, templates: &TemplateSet) -> Result<()> {
+        self.sds.set_templates(templates)
+    }
+
+    /// Dump packets that fail to decode to the quarantine file.
+    pub fn set_quarantine(&mut self, quarantine: Quarantine) {
+        self.sds.set_quarantine(quarantine);
+    }
+
+    /// Feeds granted or denied by the server so far.
+    #[inline]
+    pub fn entitlements(&self) -> &EntitlementReport {
+        &self.entitlements
+    }
+
     #[inline]
     pub fn is_subscribed(&self) -> bool {
         self.defs_count == self.defs_count_total
@@ -1,9 +1,13 @@
-use anyhow::{Result, anyhow, bail};
-use log::debug;
+use anyhow::{Context, Result, anyhow, bail};
+use log::{debug, info};
 use serde::Deserialize;
 use serde::de;
+use std::collections::HashMap;
 use std::fs::File;
-use std::path::Path;
+use std::net::Ipv4Addr;
+use std::path::{Path, PathBuf};
+
+use quotesdirectlib::templates::TemplateSet;
 
 /// Read config from YAML file
 /// # Errors
@@ -21,12 +25,38 @@ where
     }
 }
 
+/// Load FAST templates from file, if any, and check them against the typed messages.
+/// # Errors
+/// Returns an error if failed to load the templates or they do not match the messages.
+pub fn load_templates(path: Option<&Path>) -> Result<TemplateSet> {
+    let Some(path) = path else {
+        return Ok(TemplateSet::bundled());
+    };
+    let templates = TemplateSet::from_file(path)
+        .with_context(|| format!("Failed to load templates file {}", path.display()))?;
+    templates.check()?;
+    info!(
+        "Loaded templates version {} from {}",
+        templates
+            .version()
+            .map_or_else(|| "n/a".to_string(), |v| v.to_string()),
+        path.display()
+    );
+    Ok(templates)
+}
+
 #[derive(Deserialize, Debug, Clone)]
 #[serde(default = "default_sds_client_config")]
 pub struct SDSClientConfig {
     pub sds: SDSConfig,
     pub feeds: String,
     pub stop_on_disconnect: bool,
+    /// Static host name to addresses mapping that overrides DNS.
+    pub hosts: HashMap<String, Vec<Ipv4Addr>>,
+    /// File to dump packets that failed to decode to.
+    pub quarantine_file: Option<PathBuf>,
+    /// FAST templates file to use instead of the bundled templates.
+    pub templates_file: Option<PathBuf>,
 }
 
 #[must_use]
@@ -35,6 +65,9 @@ pub fn default_sds_client_config() -> SDSClientConfig {
         sds: default_sds_config(),
         feeds: String::new(),
         stop_on_disconnect: true,
+        hosts: HashMap::new(),
+        quarantine_file: None,
+        templates_file: None,
     }
 }
 
@@ -45,6 +78,8 @@ pub struct SDSConfig {
     pub port: u16,
     pub login: String,
     pub password: String,
+    /// Timeout of connection to each of the host addresses.
+    pub connect_timeout_sec: u64,
 }
 
 #[must_use]
@@ -54,6 +89,7 @@ pub fn default_sds_config() -> SDSConfig {
         port: 2222,
         login: "test".to_string(),
         password: "test".to_string(),
+        connect_timeout_sec: 5,
     }
 }
 
@@ -61,16 +97,47 @@ pub fn default_sds_config() -> SDSConfig {
 #[serde(default = "default_ffs_client_config")]
 pub struct FFSClientConfig {
     pub connection: ConnectionsConfig,
-    pub interface: Option<String>,
+    /// Interfaces (names or addresses) to join the multicast group on.
+    #[serde(alias = "interface", deserialize_with = "one_or_many")]
+    pub interfaces: Vec<String>,
     pub rcvbuf: Option<usize>,
+    /// Receive up to this many datagrams per syscall (Linux only).
+    pub batch_size: Option<usize>,
+    pub mode: ReceiveMode,
+    /// CPU to pin the busy-poll thread to.
+    pub cpu: Option<usize>,
+    /// Capacity of the busy-poll ring buffer (messages).
+    pub ring_capacity: usize,
+    /// Interval of sequence gaps and socket drops statistics reports.
+    pub stats_interval_sec: u64,
+    /// File to dump packets that failed to decode to.
+    pub quarantine_file: Option<PathBuf>,
+    /// FAST templates file to use instead of the bundled templates.
+    pub templates_file: Option<PathBuf>,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ReceiveMode {
+    /// Receive datagrams in a tokio task.
+    Tokio,
+    /// Receive datagrams in a dedicated busy-polling thread.
+    BusyPoll,
 }
 
 #[must_use]
 pub fn default_ffs_client_config() -> FFSClientConfig {
     FFSClientConfig {
         connection: default_connection_config(),
-        interface: None,
+        interfaces: Vec::new(),
         rcvbuf: None,
+        batch_size: None,
+        mode: ReceiveMode::Tokio,
+        cpu: None,
+        ring_capacity: 65536,
+        stats_interval_sec: 60,
+        quarantine_file: None,
+        templates_file: None,
     }
 }
 
@@ -79,6 +146,8 @@ pub fn default_ffs_client_config() -> FFSClientConfig {
 pub struct ConnectionsConfig {
     pub mcast_group: String,
     pub mcast_port: u16,
+    /// Source address for source-specific multicast (SSM).
+    pub mcast_source: Option<String>,
 }
 
 #[must_use]
@@ -86,5 +155,24 @@ pub fn default_connection_config() -> ConnectionsConfig {
     ConnectionsConfig {
         mcast_group: String::new(),
         mcast_port: 0,
+        mcast_source: None,
     }
 }
+
+/// Deserialize a single string or a list of strings.
+fn one_or_many<'de, D>(deserializer: D) -> Result<Vec<String>, D::Error>
+where
+    D: de::Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        One(String),
+        Many(Vec<String>),
+    }
+
+    Ok(match OneOrMany::deserialize(deserializer)? {
+        OneOrMany::One(s) => vec![s],
+        OneOrMany::Many(v) => v,
+    })
+}
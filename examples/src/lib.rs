@@ -2,9 +2,11 @@ use log::info;
 use tokio::signal::unix::SignalKind;
 use tokio_util::sync::CancellationToken;
 
+pub mod busy_poll;
 pub mod client;
 pub mod config;
 pub mod network;
+pub mod quarantine;
 
 /// Setup a signal handler for SIGINT and SIGTERM.
 /// # Panics
@@ -1,30 +1,100 @@
 use anyhow::{Context, Result, bail};
-use log::{debug, info};
+use log::{debug, info, warn};
 use socket2::{Domain, Protocol, SockAddr, Socket, Type};
-use std::net::{IpAddr, Ipv4Addr, SocketAddr, ToSocketAddrs};
-use tokio::net::UdpSocket;
+use std::collections::HashMap;
+use std::ffi::CStr;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::time::Duration;
+use tokio::net::{TcpStream, UdpSocket};
 
-#[allow(clippy::unused_async)]
-async fn lookup_host(host: &str) -> Result<Ipv4Addr> {
-    debug!("Resolving host/group: {host}");
-    if let Ok(addrs) = format!("{host}:0").to_socket_addrs() {
+#[cfg(target_os = "linux")]
+pub use self::batch::{BatchUdpSocket, Datagram};
+
+/// Maximum size of UDP datagram payload.
+pub const MAX_DATAGRAM_SIZE: usize = 65507;
+
+/// Host name resolver with static hosts override.
+#[derive(Debug, Clone, Default)]
+pub struct Resolver {
+    hosts: HashMap<String, Vec<Ipv4Addr>>,
+}
+
+impl Resolver {
+    #[must_use]
+    pub fn new(hosts: HashMap<String, Vec<Ipv4Addr>>) -> Self {
+        Self { hosts }
+    }
+
+    /// Resolve host name to all its IPv4 addresses.
+    /// Static hosts take precedence over DNS.
+    /// # Errors
+    /// Returns an error if the host cannot be resolved or has no IPv4 addresses.
+    pub async fn resolve(&self, host: &str) -> Result<Vec<Ipv4Addr>> {
+        match self.hosts.get(host) {
+            Some(addrs) if !addrs.is_empty() => {
+                debug!("Resolved {host} from static hosts: {addrs:?}");
+                Ok(addrs.clone())
+            }
+            _ => resolve_ipv4(host).await,
+        }
+    }
+
+    /// Connect to the host trying each of its addresses in order.
+    /// Returns the stream and the address that succeeded.
+    /// # Errors
+    /// Returns an error if the host cannot be resolved or connection to all its addresses failed.
+    pub async fn connect(
+        &self,
+        host: &str,
+        port: u16,
+        timeout: Duration,
+    ) -> Result<(TcpStream, SocketAddr)> {
+        let addrs = self.resolve(host).await?;
         for addr in addrs {
-            match addr.ip() {
-                IpAddr::V4(ip) => {
-                    return Ok(ip);
-                }
-                IpAddr::V6(_) => {
-                    // Ignore IPv6
+            let addr = SocketAddr::new(addr.into(), port);
+            debug!("Connecting to {host} at {addr}");
+            match tokio::time::timeout(timeout, TcpStream::connect(addr)).await {
+                Ok(Ok(stream)) => {
+                    info!("Connected to {host} at {addr}");
+                    return Ok((stream, addr));
                 }
+                Ok(Err(err)) => warn!("Failed to connect to {host} at {addr}: {err}"),
+                Err(_) => warn!("Timed out connecting to {host} at {addr}"),
             }
         }
-    } else {
-        // TODO: Resolve hostname to ipv4 using DNS?
+        bail!("Failed to connect to {host}:{port}")
     }
-    bail!("Failed to resolve host: {host}")
+}
+
+/// Resolve host name to all its IPv4 addresses using DNS.
+/// # Errors
+/// Returns an error if the host cannot be resolved or has no IPv4 addresses.
+pub async fn resolve_ipv4(host: &str) -> Result<Vec<Ipv4Addr>> {
+    debug!("Resolving host/group: {host}");
+    let addrs: Vec<Ipv4Addr> = tokio::net::lookup_host((host, 0))
+        .await
+        .with_context(|| format!("Failed to resolve host: {host}"))?
+        .filter_map(|addr| match addr.ip() {
+            IpAddr::V4(ip) => Some(ip),
+            IpAddr::V6(_) => None, // Ignore IPv6
+        })
+        .collect();
+    if addrs.is_empty() {
+        bail!("Host {host} has no IPv4 addresses");
+    }
+    Ok(addrs)
+}
+
+async fn lookup_host(host: &str) -> Result<Ipv4Addr> {
+    Ok(resolve_ipv4(host).await?[0])
 }
 
 /// Create a UDP socket and join a multicast group.
+///
+/// If `source` is given, the socket joins the group as source-specific multicast (SSM).
+/// The group is joined on each of the `interfaces` given by name (e.g. `eth1`) or address,
+/// or on the default interface if none given.
+///
 /// # Errors
 ///
 /// * any argument is invalid
@@ -33,44 +103,435 @@ async fn lookup_host(host: &str) -> Result<Ipv4Addr> {
 pub async fn make_multicast_udp_socket(
     group: &str,
     port: u16,
-    interface: &Option<String>,
+    source: &Option<String>,
+    interfaces: &[String],
     rcvbuf: &Option<usize>,
 ) -> Result<UdpSocket> {
+    let socket = make_multicast_socket(group, port, source.as_deref(), interfaces, *rcvbuf).await?;
+
+    // convert socket2::Socket to tokio::net::UdpSocket
+    let socket = UdpSocket::from_std(socket.into()).context("UdpSocket::from_std()")?;
+    Ok(socket)
+}
+
+/// Create a UDP socket that joins a multicast group and receives datagrams in batches.
+/// # Errors
+///
+/// * any argument is invalid
+/// * failed to create and setup a socket.
+///
+#[cfg(target_os = "linux")]
+pub async fn make_multicast_batch_socket(
+    group: &str,
+    port: u16,
+    source: &Option<String>,
+    interfaces: &[String],
+    rcvbuf: &Option<usize>,
+    batch_size: usize,
+) -> Result<BatchUdpSocket> {
+    let socket = make_multicast_socket(group, port, source.as_deref(), interfaces, *rcvbuf).await?;
+    BatchUdpSocket::new(socket, batch_size)
+}
+
+async fn make_multicast_socket(
+    group: &str,
+    port: u16,
+    source: Option<&str>,
+    interfaces: &[String],
+    rcvbuf: Option<usize>,
+) -> Result<Socket> {
     let Ok(group) = lookup_host(group).await else {
         bail!("Unknown multicast group: {group}")
     };
     if !group.is_multicast() {
         bail!("Group '{group}' is not a multicast address");
     }
-    let interface = match interface.as_ref() {
-        Some(addr) => lookup_host(addr).await?,
-        None => Ipv4Addr::UNSPECIFIED,
+    let source = match source {
+        Some(source) => match lookup_host(source).await {
+            Ok(addr) => Some(addr),
+            Err(_) => bail!("Unknown multicast source: {source}"),
+        },
+        None => None,
     };
+    let mut addrs = Vec::with_capacity(interfaces.len());
+    for interface in interfaces {
+        addrs.push(lookup_interface(interface).await?);
+    }
+    if addrs.is_empty() {
+        addrs.push(Ipv4Addr::UNSPECIFIED);
+    }
 
     let socket =
         Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP)).context("Socket::new()")?;
     socket
         .set_reuse_address(true)
         .context("socket.set_reuse_address()")?;
-    if let Some(rcvbuf) = rcvbuf.as_ref() {
+    if let Some(rcvbuf) = rcvbuf {
         socket
-            .set_recv_buffer_size(*rcvbuf)
+            .set_recv_buffer_size(rcvbuf)
             .context("socket.set_recv_buffer_size()")?;
+        check_recv_buffer_size(&socket, rcvbuf)?;
     }
 
     let addr_port = SocketAddr::new(group.into(), port);
-    info!("Joining multicast group: {addr_port} on interface: {interface}");
     socket
         .bind(&SockAddr::from(addr_port))
         .context("socket::bind()")?;
     socket
         .set_nonblocking(true)
         .context("socket.set_nonblocking()")?;
-    socket
-        .join_multicast_v4(&group, &interface)
-        .context("socket.join_multicast()")?;
-
-    // convert socket2::Socket to tokio::net::UdpSocket
-    let socket = UdpSocket::from_std(socket.into()).context("UdpSocket::from_std()")?;
+    for interface in &addrs {
+        if let Some(source) = &source {
+            info!(
+                "Joining multicast group: {addr_port} from source: {source} on interface: {interface}"
+            );
+            socket
+                .join_ssm_v4(source, &group, interface)
+                .context("socket.join_ssm_v4()")?;
+        } else {
+            info!("Joining multicast group: {addr_port} on interface: {interface}");
+            socket
+                .join_multicast_v4(&group, interface)
+                .context("socket.join_multicast()")?;
+        }
+    }
     Ok(socket)
 }
+
+/// Read back the effective receive buffer size and warn if the kernel granted less than requested.
+fn check_recv_buffer_size(socket: &Socket, requested: usize) -> Result<()> {
+    let granted = socket
+        .recv_buffer_size()
+        .context("socket.recv_buffer_size()")?;
+    // Linux doubles the requested value to allow space for bookkeeping overhead
+    let effective = if cfg!(target_os = "linux") {
+        granted / 2
+    } else {
+        granted
+    };
+    if effective < requested {
+        match read_rmem_max() {
+            Some(rmem_max) => warn!(
+                "Receive buffer size {effective} is less than requested {requested}, \
+                 capped by net.core.rmem_max={rmem_max}"
+            ),
+            None => warn!("Receive buffer size {effective} is less than requested {requested}"),
+        }
+    } else {
+        info!("Receive buffer size: {effective}");
+    }
+    Ok(())
+}
+
+fn read_rmem_max() -> Option<usize> {
+    std::fs::read_to_string("/proc/sys/net/core/rmem_max")
+        .ok()?
+        .trim()
+        .parse()
+        .ok()
+}
+
+/// Per-socket counter of datagrams dropped by the kernel (e.g. due to receive buffer overrun).
+///
+/// The counter is read from the `drops` column of `/proc/net/udp` (Linux only).
+#[derive(Debug, Clone, Copy)]
+pub struct DropCounter {
+    inode: u64,
+}
+
+impl DropCounter {
+    /// # Errors
+    /// Returns an error if failed to get the socket inode.
+    pub fn new(socket: &impl std::os::fd::AsRawFd) -> std::io::Result<Self> {
+        let mut stat: libc::stat = unsafe { std::mem::zeroed() };
+        if unsafe { libc::fstat(socket.as_raw_fd(), &raw mut stat) } != 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        Ok(Self { inode: stat.st_ino })
+    }
+
+    /// Read the current number of dropped datagrams.
+    /// Returns `None` if the socket is not found.
+    /// # Errors
+    /// Returns an error if failed to read `/proc/net/udp`.
+    pub fn read(&self) -> std::io::Result<Option<u64>> {
+        let table = std::fs::read_to_string("/proc/net/udp")?;
+        let inode = self.inode.to_string();
+        Ok(table.lines().skip(1).find_map(|line| {
+            // sl local_address rem_address st tx_queue:rx_queue tr:tm->when retrnsmt uid timeout inode ref pointer drops
+            let cols: Vec<&str> = line.split_whitespace().collect();
+            if cols.len() >= 13 && cols[9] == inode {
+                cols[12].parse().ok()
+            } else {
+                None
+            }
+        }))
+    }
+}
+
+/// Resolve interface given by name, IPv4 address or hostname to its IPv4 address.
+async fn lookup_interface(interface: &str) -> Result<Ipv4Addr> {
+    if let Ok(addr) = interface.parse::<Ipv4Addr>() {
+        return Ok(addr);
+    }
+    match interface_addrs(interface).context("getifaddrs()")? {
+        Some(addrs) => match addrs.first() {
+            Some(addr) => {
+                debug!("Interface {interface} has address {addr}");
+                Ok(*addr)
+            }
+            None => bail!("Interface '{interface}' has no IPv4 address"),
+        },
+        None => match lookup_host(interface).await {
+            Ok(addr) => Ok(addr),
+            Err(_) => bail!("Unknown interface: {interface}"),
+        },
+    }
+}
+
+/// Returns IPv4 addresses of the network interface or `None` if there is no interface with such name.
+fn interface_addrs(name: &str) -> std::io::Result<Option<Vec<Ipv4Addr>>> {
+    let mut ifap: *mut libc::ifaddrs = std::ptr::null_mut();
+    if unsafe { libc::getifaddrs(&raw mut ifap) } != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    let mut found = false;
+    let mut addrs = Vec::new();
+    let mut ifa = ifap;
+    while !ifa.is_null() {
+        let entry = unsafe { &*ifa };
+        let ifa_name = unsafe { CStr::from_ptr(entry.ifa_name) };
+        if ifa_name.to_bytes() == name.as_bytes() {
+            found = true;
+            if !entry.ifa_addr.is_null()
+                && i32::from(unsafe { (*entry.ifa_addr).sa_family }) == libc::AF_INET
+            {
+                let sin: libc::sockaddr_in =
+                    unsafe { std::ptr::read_unaligned(entry.ifa_addr.cast()) };
+                addrs.push(Ipv4Addr::from(u32::from_be(sin.sin_addr.s_addr)));
+            }
+        }
+        ifa = entry.ifa_next;
+    }
+    unsafe { libc::freeifaddrs(ifap) };
+    Ok(found.then_some(addrs))
+}
+
+#[cfg(target_os = "linux")]
+mod batch {
+    use anyhow::{Context, Result};
+    use socket2::Socket;
+    use std::io;
+    use std::mem::{size_of, zeroed};
+    use std::os::fd::{AsRawFd, RawFd};
+    use std::ptr::null_mut;
+    use std::time::{Duration, SystemTime};
+    use tokio::io::Interest;
+    use tokio::io::unix::AsyncFd;
+
+    use super::MAX_DATAGRAM_SIZE;
+
+    // Enough space for `SCM_TIMESTAMPNS` and `SO_RXQ_OVFL` control messages.
+    const CMSG_BUFFER_SIZE: usize = 64;
+
+    /// UDP datagram received by [`BatchUdpSocket`].
+    #[derive(Debug)]
+    pub struct Datagram<'a> {
+        pub data: &'a [u8],
+        /// Kernel receive timestamp (`SO_TIMESTAMPNS`).
+        pub timestamp: Option<SystemTime>,
+    }
+
+    /// UDP socket that reads many datagrams per syscall using `recvmmsg(2)`
+    /// and attaches kernel receive timestamps to each of them.
+    ///
+    /// It also tracks the number of datagrams dropped by the kernel reported via `SO_RXQ_OVFL`.
+    pub struct BatchUdpSocket {
+        fd: AsyncFd<Socket>,
+        batch: Batch,
+    }
+
+    struct Batch {
+        buffers: Vec<u8>,
+        controls: Vec<[u8; CMSG_BUFFER_SIZE]>,
+        /// Point into `buffers`, heap allocations do not move with the struct.
+        iovecs: Vec<libc::iovec>,
+        /// Point into `iovecs` and `controls`.
+        headers: Vec<libc::mmsghdr>,
+        lengths: Vec<usize>,
+        timestamps: Vec<Option<SystemTime>>,
+        received: usize,
+        drops: u32,
+    }
+
+    impl BatchUdpSocket {
+        /// Wrap non-blocking socket and enable `SO_TIMESTAMPNS` and `SO_RXQ_OVFL` on it.
+        /// # Errors
+        /// Returns an error if failed to setup the socket or register it in the reactor.
+        pub fn new(socket: Socket, batch_size: usize) -> Result<Self> {
+            let batch_size = batch_size.max(1);
+            enable_option(&socket, libc::SO_TIMESTAMPNS).context("setsockopt(SO_TIMESTAMPNS)")?;
+            enable_option(&socket, libc::SO_RXQ_OVFL).context("setsockopt(SO_RXQ_OVFL)")?;
+            let fd = AsyncFd::with_interest(socket, Interest::READABLE)
+                .context("AsyncFd::with_interest()")?;
+            Ok(Self {
+                fd,
+                batch: Batch::new(batch_size),
+            })
+        }
+
+        /// Wait for datagrams and receive as many of them as fits into the batch.
+        /// Returns iterator over received datagrams.
+        /// # Errors
+        /// Returns an error if `recvmmsg(2)` fails.
+        pub async fn recv(&mut self) -> io::Result<impl Iterator<Item = Datagram<'_>>> {
+            loop {
+                let mut guard = self.fd.readable().await?;
+                if let Ok(result) = guard.try_io(|fd| self.batch.recvmmsg(fd.as_raw_fd())) {
+                    self.batch.received = result?;
+                    break;
+                }
+            }
+            Ok(self.batch.datagrams())
+        }
+
+        /// Number of datagrams dropped by the kernel since the socket was created.
+        #[must_use]
+        pub fn drops(&self) -> u32 {
+            self.batch.drops
+        }
+    }
+
+    impl AsRawFd for BatchUdpSocket {
+        fn as_raw_fd(&self) -> RawFd {
+            self.fd.as_raw_fd()
+        }
+    }
+
+    #[allow(clippy::cast_possible_truncation)]
+    fn enable_option(socket: &Socket, option: libc::c_int) -> io::Result<()> {
+        let enable: libc::c_int = 1;
+        let res = unsafe {
+            libc::setsockopt(
+                socket.as_raw_fd(),
+                libc::SOL_SOCKET,
+                option,
+                (&raw const enable).cast(),
+                size_of::<libc::c_int>() as libc::socklen_t,
+            )
+        };
+        if res != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    // SAFETY: the raw pointers only refer to the batch's own heap buffers.
+    unsafe impl Send for Batch {}
+
+    impl Batch {
+        fn new(batch_size: usize) -> Self {
+            let mut batch = Self {
+                buffers: vec![0u8; batch_size * MAX_DATAGRAM_SIZE],
+                controls: vec![[0u8; CMSG_BUFFER_SIZE]; batch_size],
+                iovecs: Vec::with_capacity(batch_size),
+                headers: Vec::with_capacity(batch_size),
+                lengths: vec![0; batch_size],
+                timestamps: vec![None; batch_size],
+                received: 0,
+                drops: 0,
+            };
+            batch.iovecs = batch
+                .buffers
+                .chunks_exact_mut(MAX_DATAGRAM_SIZE)
+                .map(|buf| libc::iovec {
+                    iov_base: buf.as_mut_ptr().cast(),
+                    iov_len: buf.len(),
+                })
+                .collect();
+            batch.headers = batch
+                .iovecs
+                .iter_mut()
+                .zip(batch.controls.iter_mut())
+                .map(|(iov, control)| {
+                    let mut hdr: libc::mmsghdr = unsafe { zeroed() };
+                    hdr.msg_hdr.msg_name = null_mut();
+                    hdr.msg_hdr.msg_iov = iov;
+                    hdr.msg_hdr.msg_iovlen = 1;
+                    hdr.msg_hdr.msg_control = control.as_mut_ptr().cast();
+                    hdr
+                })
+                .collect();
+            batch
+        }
+
+        fn datagrams(&self) -> impl Iterator<Item = Datagram<'_>> {
+            (0..self.received).map(|i| {
+                let offset = i * MAX_DATAGRAM_SIZE;
+                Datagram {
+                    data: &self.buffers[offset..offset + self.lengths[i]],
+                    timestamp: self.timestamps[i],
+                }
+            })
+        }
+
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        fn recvmmsg(&mut self, fd: libc::c_int) -> io::Result<usize> {
+            let batch_size = self.headers.len();
+            // the kernel overwrites the lengths on each call
+            for hdr in &mut self.headers {
+                hdr.msg_len = 0;
+                hdr.msg_hdr.msg_controllen = CMSG_BUFFER_SIZE as _;
+            }
+
+            let res = unsafe {
+                libc::recvmmsg(
+                    fd,
+                    self.headers.as_mut_ptr(),
+                    batch_size as libc::c_uint,
+                    libc::MSG_DONTWAIT,
+                    null_mut(),
+                )
+            };
+            if res < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            let received = res as usize;
+            for (i, hdr) in self.headers.iter().take(received).enumerate() {
+                let (timestamp, drops) = unsafe { read_control(&hdr.msg_hdr) };
+                self.lengths[i] = hdr.msg_len as usize;
+                self.timestamps[i] = timestamp;
+                if let Some(drops) = drops {
+                    self.drops = drops;
+                }
+            }
+            Ok(received)
+        }
+    }
+
+    /// Extract `SCM_TIMESTAMPNS` and `SO_RXQ_OVFL` from the control messages of received datagram.
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    unsafe fn read_control(hdr: &libc::msghdr) -> (Option<SystemTime>, Option<u32>) {
+        let mut timestamp = None;
+        let mut drops = None;
+        unsafe {
+            let mut cmsg = libc::CMSG_FIRSTHDR(hdr);
+            while !cmsg.is_null() {
+                if (*cmsg).cmsg_level == libc::SOL_SOCKET {
+                    if (*cmsg).cmsg_type == libc::SCM_TIMESTAMPNS {
+                        let ts: libc::timespec =
+                            std::ptr::read_unaligned(libc::CMSG_DATA(cmsg).cast());
+                        timestamp = Some(
+                            SystemTime::UNIX_EPOCH
+                                + Duration::new(ts.tv_sec as u64, ts.tv_nsec as u32),
+                        );
+                    } else if (*cmsg).cmsg_type == libc::SO_RXQ_OVFL {
+                        drops = Some(std::ptr::read_unaligned(libc::CMSG_DATA(cmsg).cast()));
+                    }
+                }
+                cmsg = libc::CMSG_NXTHDR(hdr, cmsg);
+            }
+        }
+        (timestamp, drops)
+    }
+}
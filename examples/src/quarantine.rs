@@ -0,0 +1,44 @@
+//! Quarantine file of packets that failed to decode.
+//!
+//! Packets are written with the TCP framing, so the file can be replayed
+//! with `TCPPacket::read` for later analysis.
+//!
+use anyhow::{Context, Result};
+use log::info;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use quotesdirectlib::sync::packets::TCPPacket;
+
+pub struct Quarantine {
+    writer: BufWriter<File>,
+}
+
+impl Quarantine {
+    /// Create the quarantine file, truncating it if it exists.
+    /// # Errors
+    /// Returns an error if the file cannot be created.
+    pub fn create(path: &Path) -> Result<Self> {
+        let file = File::create(path)
+            .with_context(|| format!("Failed to create quarantine file {}", path.display()))?;
+        info!("Quarantining undecodable packets to {}", path.display());
+        Ok(Self {
+            writer: BufWriter::new(file),
+        })
+    }
+
+    /// Append the packet and flush the file.
+    /// # Errors
+    /// Returns an error if the file cannot be written.
+    pub fn write(&mut self, seq_num: u32, sub_channel: u8, payload: &[u8]) -> Result<()> {
+        TCPPacket {
+            seq_num,
+            sub_channel,
+            payload: payload.to_vec(),
+        }
+        .write(&mut self.writer)?;
+        self.writer.flush()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,513 @@
+//! # OHLCV bars
+//!
+//! Aggregates the [trade tape](crate::trades) into time, tick or volume bars per instrument.
+//!
+//! Bars are aligned to trading sessions from `SecurityDefinition.TradingSessions`:
+//! time bars start at the session start and never span two sessions,
+//! tick and volume bars are closed at the session end.
+//! Without known sessions time bars are aligned to the Unix epoch.
+//!
+//! Bars are driven by trade times (`MDEntryTime`) rather than the wall clock,
+//! so the same builder works over recordings. Live consumers call [`BarBuilder::flush_until`]
+//! periodically to close bars of instruments that stopped trading.
+//!
+//! A time bar is closed once a trade at or after its end plus the grace period is seen.
+//! Tick and volume bars are closed as soon as they are complete, trades up to the grace period
+//! before their last trade still go into the next bar.
+//! Trades arriving later than that are handled according to [`LateTrade`].
+//!
+//! The close price is the price of the latest trade by time, not by arrival.
+//!
+//! # Examples
+//!
+//! ```rust,ignore
+//! use chrono::TimeDelta;
+//! use quotesdirectlib::bars::{Bar, BarBuilder, BarKind};
+//!
+//! let mut bars = BarBuilder::new(BarKind::Time(TimeDelta::minutes(1)));
+//! println!("{}", Bar::CSV_HEADER);
+//! for trade in tape.process(&msg) {
+//!     for bar in bars.process(&trade) {
+//!         println!("{}", bar.to_csv());
+//!     }
+//! }
+//! ```
+//!
+use chrono::{DateTime, NaiveDate, SecondsFormat, TimeDelta, Utc};
+use fastlib::Decimal;
+use serde::{Serialize, Serializer};
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap};
+
+use crate::calendar::Session;
+use crate::fast::{SecurityDefinition, TradingSession};
+use crate::market::cmp_decimal;
+use crate::trades::Trade;
+
+/// Bar type.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum BarKind {
+    /// Bars of fixed duration.
+    Time(TimeDelta),
+    /// Bars of fixed number of trades.
+    Tick(u32),
+    /// Bars closed when traded volume reaches the threshold.
+    Volume(u64),
+}
+
+/// Handling of trades for bars that are already closed.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub enum LateTrade {
+    /// Drop the trade and count it in [`BarBuilder::late_trades`].
+    #[default]
+    Drop,
+    /// Add the trade to the instrument's latest open bar.
+    IntoCurrent,
+}
+
+/// OHLCV bar.
+#[derive(Debug, PartialEq, Clone, Serialize)]
+pub struct Bar {
+    pub security_id: u32,
+    pub trade_date: Option<NaiveDate>,
+    /// Window start for time bars, first trade time for tick and volume bars.
+    pub start: DateTime<Utc>,
+    /// Window end (exclusive) for time bars, last trade time for tick and volume bars.
+    pub end: DateTime<Utc>,
+    #[serde(serialize_with = "serialize_decimal")]
+    pub open: Decimal,
+    #[serde(serialize_with = "serialize_decimal")]
+    pub high: Decimal,
+    #[serde(serialize_with = "serialize_decimal")]
+    pub low: Decimal,
+    #[serde(serialize_with = "serialize_decimal")]
+    pub close: Decimal,
+    pub volume: u64,
+    pub trades: u32,
+    /// Time of the latest trade, sets the close price.
+    #[serde(skip)]
+    last_time: DateTime<Utc>,
+}
+
+impl Bar {
+    pub const CSV_HEADER: &'static str =
+        "security_id,trade_date,start,end,open,high,low,close,volume,trades";
+
+    fn new(
+        trade: &Trade,
+        ts: DateTime<Utc>,
+        trade_date: Option<NaiveDate>,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Self {
+        Self {
+            security_id: trade.security_id,
+            trade_date,
+            start,
+            end,
+            open: trade.price.clone(),
+            high: trade.price.clone(),
+            low: trade.price.clone(),
+            close: trade.price.clone(),
+            volume: 0,
+            trades: 0,
+            last_time: ts,
+        }
+    }
+
+    fn add(&mut self, trade: &Trade, ts: DateTime<Utc>) {
+        if self.trades > 0 {
+            if cmp_decimal(&trade.price, &self.high) == Ordering::Greater {
+                self.high = trade.price.clone();
+            }
+            if cmp_decimal(&trade.price, &self.low) == Ordering::Less {
+                self.low = trade.price.clone();
+            }
+            if ts >= self.last_time {
+                self.close = trade.price.clone();
+                self.last_time = ts;
+            }
+        }
+        self.volume += u64::from(trade.size.unsigned_abs());
+        self.trades += 1;
+    }
+
+    /// Format the bar as a CSV record matching [`Bar::CSV_HEADER`].
+    #[must_use]
+    pub fn to_csv(&self) -> String {
+        format!(
+            "{},{},{},{},{},{},{},{},{},{}",
+            self.security_id,
+            self.trade_date.map(|d| d.to_string()).unwrap_or_default(),
+            self.start.to_rfc3339_opts(SecondsFormat::Millis, true),
+            self.end.to_rfc3339_opts(SecondsFormat::Millis, true),
+            self.open,
+            self.high,
+            self.low,
+            self.close,
+            self.volume,
+            self.trades,
+        )
+    }
+}
+
+fn serialize_decimal<S: Serializer>(value: &Decimal, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(value)
+}
+
+#[derive(Debug, Default)]
+struct InstrumentBars {
+    sessions: Vec<Session>,
+    /// Open bars by start time. Tick and volume bars have at most one open bar.
+    open: BTreeMap<DateTime<Utc>, (Bar, Option<Session>)>,
+    /// End of the latest closed bar, less the lag; trades before it are late.
+    watermark: Option<DateTime<Utc>>,
+}
+
+impl InstrumentBars {
+    fn session_at(&self, ts: DateTime<Utc>) -> Option<Session> {
+        self.sessions.iter().find(|s| s.contains(ts)).copied()
+    }
+
+    /// Close open bars for which `is_done` returns true,
+    /// trades up to `lag` before their end are still accepted.
+    fn close(
+        &mut self,
+        bars: &mut Vec<Bar>,
+        lag: TimeDelta,
+        is_done: impl Fn(&Bar, Option<&Session>) -> bool,
+    ) {
+        let done: Vec<_> = self
+            .open
+            .iter()
+            .filter(|(_, (bar, session))| is_done(bar, session.as_ref()))
+            .map(|(start, _)| *start)
+            .collect();
+        for start in done {
+            if let Some((bar, _)) = self.open.remove(&start) {
+                self.watermark = self.watermark.max(Some(bar.end - lag));
+                bars.push(bar);
+            }
+        }
+    }
+}
+
+/// Builds bars from trades of multiple instruments.
+#[derive(Debug)]
+pub struct BarBuilder {
+    kind: BarKind,
+    grace: TimeDelta,
+    late: LateTrade,
+    instruments: HashMap<u32, InstrumentBars>,
+    late_trades: u64,
+}
+
+impl BarBuilder {
+    #[must_use]
+    pub fn new(kind: BarKind) -> Self {
+        Self {
+            kind,
+            grace: TimeDelta::zero(),
+            late: LateTrade::default(),
+            instruments: HashMap::new(),
+            late_trades: 0,
+        }
+    }
+
+    /// Time to wait for out of order trades before a time bar is closed.
+    /// Tick and volume bars are closed without waiting, trades up to `grace` before
+    /// the last trade of a closed bar are added to the next one instead of being late.
+    #[must_use]
+    pub fn with_grace(mut self, grace: TimeDelta) -> Self {
+        self.grace = grace;
+        self
+    }
+
+    #[must_use]
+    pub fn with_late_trade(mut self, late: LateTrade) -> Self {
+        self.late = late;
+        self
+    }
+
+    /// Set trading sessions of the instrument from its definition.
+    pub fn add_definition(&mut self, definition: &SecurityDefinition) {
+        self.set_sessions(definition.security_id, &definition.trading_sessions);
+    }
+
+    /// Set trading sessions of the instrument.
+    pub fn set_sessions(&mut self, security_id: u32, sessions: &[TradingSession]) {
+        let instrument = self.instruments.entry(security_id).or_default();
+        instrument.sessions = sessions.iter().filter_map(Session::from_fix).collect();
+        instrument.sessions.sort_by_key(|s| s.start);
+    }
+
+    /// Add a trade and return bars completed by it.
+    /// Trades without `time` are ignored.
+    pub fn process(&mut self, trade: &Trade) -> Vec<Bar> {
+        let mut bars = Vec::new();
+        let Some(ts) = trade.time else {
+            return bars;
+        };
+        let lag = self.watermark_lag();
+        let instrument = self.instruments.entry(trade.security_id).or_default();
+        let session = instrument.session_at(ts);
+
+        // close bars the trade is past
+        let grace = self.grace;
+        match self.kind {
+            BarKind::Time(_) => instrument.close(&mut bars, lag, |bar, _| bar.end + grace <= ts),
+            BarKind::Tick(_) | BarKind::Volume(_) => {
+                instrument.close(&mut bars, lag, |_, s| s.copied() != session);
+            }
+        }
+
+        if instrument.watermark.is_some_and(|watermark| ts < watermark) {
+            match (self.late, instrument.open.values_mut().next_back()) {
+                (LateTrade::IntoCurrent, Some((bar, _))) => bar.add(trade, ts),
+                _ => self.late_trades += 1,
+            }
+            return bars;
+        }
+
+        let trade_date = session.map(|s| s.trade_date).or(trade.trade_date);
+        match self.kind {
+            BarKind::Time(period) => {
+                let (start, end) = time_window(ts, period, session.as_ref());
+                instrument
+                    .open
+                    .entry(start)
+                    .or_insert_with(|| (Bar::new(trade, ts, trade_date, start, end), session))
+                    .0
+                    .add(trade, ts);
+            }
+            BarKind::Tick(_) | BarKind::Volume(_) => {
+                let start = instrument.open.keys().next().copied().unwrap_or(ts);
+                let (bar, _) = instrument
+                    .open
+                    .entry(start)
+                    .or_insert_with(|| (Bar::new(trade, ts, trade_date, ts, ts), session));
+                bar.add(trade, ts);
+                bar.end = bar.end.max(ts);
+                let complete = match self.kind {
+                    BarKind::Tick(count) => bar.trades >= count,
+                    BarKind::Volume(volume) => bar.volume >= volume,
+                    BarKind::Time(_) => false,
+                };
+                if complete {
+                    instrument.close(&mut bars, lag, |_, _| true);
+                }
+            }
+        }
+        bars
+    }
+
+    /// Close bars that can no longer receive trades at time `now`:
+    /// time bars ended before `now` minus the grace period
+    /// and tick or volume bars of sessions ended by then.
+    pub fn flush_until(&mut self, now: DateTime<Utc>) -> Vec<Bar> {
+        let grace = self.grace;
+        let lag = self.watermark_lag();
+        let kind = self.kind;
+        let mut bars = Vec::new();
+        for instrument in self.instruments.values_mut() {
+            instrument.close(&mut bars, lag, |bar, session| match kind {
+                BarKind::Time(_) => bar.end + grace <= now,
+                BarKind::Tick(_) | BarKind::Volume(_) => {
+                    session.is_some_and(|s| s.end + grace <= now)
+                }
+            });
+        }
+        bars.sort_by_key(|bar| (bar.start, bar.security_id));
+        bars
+    }
+
+    /// Close all open bars, e.g. at the end of a recording.
+    pub fn flush(&mut self) -> Vec<Bar> {
+        let lag = self.watermark_lag();
+        let mut bars = Vec::new();
+        for instrument in self.instruments.values_mut() {
+            instrument.close(&mut bars, lag, |_, _| true);
+        }
+        bars.sort_by_key(|bar| (bar.start, bar.security_id));
+        bars
+    }
+
+    /// Time bars end at the window end, tick and volume bars at their last trade,
+    /// which out of order trades can precede by up to the grace period.
+    fn watermark_lag(&self) -> TimeDelta {
+        match self.kind {
+            BarKind::Time(_) => TimeDelta::zero(),
+            BarKind::Tick(_) | BarKind::Volume(_) => self.grace,
+        }
+    }
+
+    /// Number of dropped late trades.
+    #[must_use]
+    pub fn late_trades(&self) -> u64 {
+        self.late_trades
+    }
+}
+
+/// Window of a time bar containing `ts`, aligned to the session start and cut at its end.
+fn time_window(
+    ts: DateTime<Utc>,
+    period: TimeDelta,
+    session: Option<&Session>,
+) -> (DateTime<Utc>, DateTime<Utc>) {
+    let anchor = session.map_or(DateTime::UNIX_EPOCH, |s| s.start);
+    let period_ms = period.num_milliseconds().max(1);
+    let offset = (ts - anchor).num_milliseconds().div_euclid(period_ms) * period_ms;
+    let start = anchor + TimeDelta::milliseconds(offset);
+    let end = start + TimeDelta::milliseconds(period_ms);
+    (start, session.map_or(end, |s| end.min(s.end)))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::market::Aggressor;
+    use crate::time::utc_timestamp;
+
+    fn trade(time: u64, price: i64, size: i32) -> Trade {
+        Trade {
+            security_id: 1,
+            rpt_seq: 0,
+            trade_date: NaiveDate::from_ymd_opt(2025, 6, 20),
+            time: utc_timestamp(time),
+            price: Decimal::new(-2, price),
+            size,
+            aggressor: Aggressor::None,
+            cumulative_volume: 0,
+            workup: None,
+            trade_condition: None,
+            party_ids: vec![],
+        }
+    }
+
+    #[test]
+    fn test_time_bars() {
+        let mut builder =
+            BarBuilder::new(BarKind::Time(TimeDelta::minutes(1))).with_grace(TimeDelta::seconds(1));
+        assert!(
+            builder
+                .process(&trade(20250620100000100, 100, 1))
+                .is_empty()
+        );
+        assert!(
+            builder
+                .process(&trade(20250620100030000, 102, 2))
+                .is_empty()
+        );
+        // within grace period the bar is still open
+        assert!(
+            builder
+                .process(&trade(20250620100100500, 101, 1))
+                .is_empty()
+        );
+        assert!(builder.process(&trade(20250620100059000, 99, 1)).is_empty());
+
+        let bars = builder.process(&trade(20250620100101000, 101, 1));
+        assert_eq!(bars.len(), 1);
+        let bar = &bars[0];
+        assert_eq!(bar.start, utc_timestamp(20250620100000000).unwrap());
+        assert_eq!(bar.end, utc_timestamp(20250620100100000).unwrap());
+        assert_eq!(bar.open, Decimal::new(-2, 100));
+        assert_eq!(bar.high, Decimal::new(-2, 102));
+        assert_eq!(bar.low, Decimal::new(-2, 99));
+        assert_eq!(bar.close, Decimal::new(-2, 99));
+        assert_eq!(bar.volume, 4);
+        assert_eq!(bar.trades, 3);
+        assert_eq!(
+            bar.to_csv(),
+            "1,2025-06-20,2025-06-20T10:00:00.000Z,2025-06-20T10:01:00.000Z,1.00,1.02,0.99,0.99,4,3"
+        );
+
+        // late trade
+        assert!(builder.process(&trade(20250620100059500, 99, 1)).is_empty());
+        assert_eq!(builder.late_trades(), 1);
+
+        let bars = builder.flush();
+        assert_eq!(bars.len(), 1);
+        assert_eq!(bars[0].trades, 2);
+    }
+
+    #[test]
+    fn test_session_alignment() {
+        let mut builder = BarBuilder::new(BarKind::Time(TimeDelta::hours(1)));
+        builder.set_sessions(
+            1,
+            &[TradingSession {
+                trade_date: 20250620,
+                trad_ses_start_time: 20250619223000000,
+                trad_ses_open_time: 20250619223000000,
+                trad_ses_close_time: 20250620211500000,
+                trad_ses_end_time: 20250620211500000,
+            }],
+        );
+        builder.process(&trade(20250620210000000, 100, 1));
+        let bars = builder.flush_until(utc_timestamp(20250620211500000).unwrap());
+        assert_eq!(bars.len(), 1);
+        assert_eq!(bars[0].start, utc_timestamp(20250620203000000).unwrap());
+        assert_eq!(bars[0].end, utc_timestamp(20250620211500000).unwrap());
+    }
+
+    #[test]
+    fn test_volume_bars() {
+        let mut builder = BarBuilder::new(BarKind::Volume(5));
+        assert!(
+            builder
+                .process(&trade(20250620100000000, 100, 3))
+                .is_empty()
+        );
+        let bars = builder.process(&trade(20250620100001000, 101, 3));
+        assert_eq!(bars.len(), 1);
+        assert_eq!(bars[0].volume, 6);
+        assert_eq!(bars[0].end, utc_timestamp(20250620100001000).unwrap());
+
+        let mut builder = BarBuilder::new(BarKind::Tick(2));
+        assert!(
+            builder
+                .process(&trade(20250620100000000, 100, 3))
+                .is_empty()
+        );
+        assert_eq!(builder.process(&trade(20250620100000000, 101, 3)).len(), 1);
+    }
+
+    #[test]
+    fn test_close_by_trade_time() {
+        let mut builder = BarBuilder::new(BarKind::Time(TimeDelta::minutes(1)));
+        builder.process(&trade(20250620100010000, 100, 1));
+        builder.process(&trade(20250620100030000, 102, 1));
+        // arrives last but traded earlier
+        builder.process(&trade(20250620100020000, 101, 1));
+        let bars = builder.flush();
+        assert_eq!(bars.len(), 1);
+        assert_eq!(bars[0].close, Decimal::new(-2, 102));
+        assert_eq!(bars[0].trades, 3);
+    }
+
+    #[test]
+    fn test_tick_bars_grace() {
+        let mut builder = BarBuilder::new(BarKind::Tick(2));
+        builder.process(&trade(20250620100000000, 100, 1));
+        assert_eq!(builder.process(&trade(20250620100001000, 101, 1)).len(), 1);
+        // slightly earlier than the last trade of the closed bar
+        builder.process(&trade(20250620100000900, 102, 1));
+        assert_eq!(builder.late_trades(), 1);
+
+        let mut builder =
+            BarBuilder::new(BarKind::Tick(2)).with_grace(TimeDelta::milliseconds(500));
+        builder.process(&trade(20250620100000000, 100, 1));
+        assert_eq!(builder.process(&trade(20250620100001000, 101, 1)).len(), 1);
+        builder.process(&trade(20250620100000900, 102, 1));
+        assert_eq!(builder.late_trades(), 0);
+        // beyond the grace period
+        builder.process(&trade(20250620100000400, 103, 1));
+        assert_eq!(builder.late_trades(), 1);
+
+        let bars = builder.flush();
+        assert_eq!(bars.len(), 1);
+        assert_eq!(bars[0].start, utc_timestamp(20250620100000900).unwrap());
+        assert_eq!(bars[0].close, Decimal::new(-2, 102));
+    }
+}
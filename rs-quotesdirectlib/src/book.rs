@@ -0,0 +1,313 @@
+//! # Order book
+//!
+//! Price level (MBP) order book of an instrument maintained from [`MarketEvent`]s.
+//! Outright and implied levels are kept separately.
+//!
+//! The book also follows the instrument's trading status and reports itself not tradable
+//! while the instrument is halted, closed or in pre-open.
+//! Group-level status messages carry no `SecurityID`, so apply them through
+//! [`StatusRegistry`](crate::status::StatusRegistry) transitions.
+//!
+//! # Examples
+//!
+//! ```rust,ignore
+//! use quotesdirectlib::book::OrderBook;
+//! use quotesdirectlib::market::normalize;
+//!
+//! let mut book = OrderBook::new(security_id);
+//! for event in normalize(&msg) {
+//!     book.apply_event(&event);
+//! }
+//! if book.is_tradable() {
+//!     println!("{:?} / {:?}", book.best_bid(), book.best_offer());
+//! }
+//! ```
+//!
+use fastlib::Decimal;
+use std::collections::HashMap;
+
+use crate::market::{BookUpdate, MarketEvent, SessionEvent, Side, TradingStatus, UpdateAction};
+use crate::status::StatusTransition;
+
+/// Price level.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Level {
+    pub price: Decimal,
+    pub size: i32,
+}
+
+/// Price level order book of one instrument.
+#[derive(Debug, Clone)]
+pub struct OrderBook {
+    security_id: u32,
+    bids: Vec<Level>,
+    offers: Vec<Level>,
+    implied_bids: Vec<Level>,
+    implied_offers: Vec<Level>,
+    status: Option<TradingStatus>,
+    rpt_seq: Option<u32>,
+}
+
+impl OrderBook {
+    #[must_use]
+    pub fn new(security_id: u32) -> Self {
+        Self {
+            security_id,
+            bids: Vec::new(),
+            offers: Vec::new(),
+            implied_bids: Vec::new(),
+            implied_offers: Vec::new(),
+            status: None,
+            rpt_seq: None,
+        }
+    }
+
+    /// Apply a market event. Events of other instruments are ignored.
+    pub fn apply_event(&mut self, event: &MarketEvent) {
+        match event {
+            MarketEvent::BookUpdate(update) => self.apply(update),
+            MarketEvent::SessionEvent(SessionEvent::EmptyBook { security_id, .. })
+                if *security_id == self.security_id =>
+            {
+                self.clear();
+            }
+            MarketEvent::SessionEvent(SessionEvent::Snapshot {
+                security_id,
+                rpt_seq,
+                ..
+            }) if *security_id == self.security_id => {
+                // snapshot levels follow as new entries
+                self.clear();
+                self.rpt_seq = Some(*rpt_seq);
+            }
+            MarketEvent::StatusChange(change) if change.security_id == Some(self.security_id) => {
+                self.set_status(change.status);
+            }
+            _ => {}
+        }
+    }
+
+    /// Apply a price level update. Updates of other instruments are ignored.
+    pub fn apply(&mut self, update: &BookUpdate) {
+        if update.security_id != self.security_id {
+            return;
+        }
+        if let Some(rpt_seq) = update.rpt_seq {
+            self.rpt_seq = Some(rpt_seq);
+        }
+        let levels = match (update.side, update.implied) {
+            (Side::Bid, false) => &mut self.bids,
+            (Side::Offer, false) => &mut self.offers,
+            (Side::Bid, true) => &mut self.implied_bids,
+            (Side::Offer, true) => &mut self.implied_offers,
+        };
+        // MDPriceLevel is 1-based
+        let index = update.level.unwrap_or(1).saturating_sub(1) as usize;
+        let level = || {
+            Some(Level {
+                price: update.price.clone()?,
+                size: update.size.unwrap_or(0),
+            })
+        };
+        match update.action {
+            UpdateAction::New => {
+                if let Some(level) = level() {
+                    levels.insert(index.min(levels.len()), level);
+                }
+            }
+            UpdateAction::Change | UpdateAction::Overlay => {
+                if let Some(level) = level() {
+                    match levels.get_mut(index) {
+                        Some(existing) => *existing = level,
+                        None => levels.push(level),
+                    }
+                }
+            }
+            UpdateAction::Delete => {
+                if index < levels.len() {
+                    levels.remove(index);
+                }
+            }
+            UpdateAction::DeleteThru => levels.clear(),
+            UpdateAction::DeleteFrom => {
+                levels.drain(..(index + 1).min(levels.len()));
+            }
+            UpdateAction::Other(_) => {}
+        }
+    }
+
+    /// Apply a status transition from the registry. Transitions of other instruments are ignored.
+    pub fn apply_status(&mut self, transition: &StatusTransition) {
+        if transition.security_id == self.security_id {
+            self.set_status(transition.to);
+        }
+    }
+
+    /// Set the instrument's trading status.
+    pub fn set_status(&mut self, status: TradingStatus) {
+        if status != TradingStatus::NoChange {
+            self.status = Some(status);
+        }
+    }
+
+    /// Remove all price levels.
+    pub fn clear(&mut self) {
+        self.bids.clear();
+        self.offers.clear();
+        self.implied_bids.clear();
+        self.implied_offers.clear();
+    }
+
+    /// The book is tradable unless the instrument's status says otherwise.
+    #[must_use]
+    pub fn is_tradable(&self) -> bool {
+        self.status.is_none_or(TradingStatus::is_tradable)
+    }
+
+    #[must_use]
+    pub fn security_id(&self) -> u32 {
+        self.security_id
+    }
+
+    #[must_use]
+    pub fn status(&self) -> Option<TradingStatus> {
+        self.status
+    }
+
+    /// `RptSeq` of the last applied update or snapshot.
+    #[must_use]
+    pub fn rpt_seq(&self) -> Option<u32> {
+        self.rpt_seq
+    }
+
+    #[must_use]
+    pub fn bids(&self) -> &[Level] {
+        &self.bids
+    }
+
+    #[must_use]
+    pub fn offers(&self) -> &[Level] {
+        &self.offers
+    }
+
+    #[must_use]
+    pub fn implied_bids(&self) -> &[Level] {
+        &self.implied_bids
+    }
+
+    #[must_use]
+    pub fn implied_offers(&self) -> &[Level] {
+        &self.implied_offers
+    }
+
+    #[must_use]
+    pub fn best_bid(&self) -> Option<&Level> {
+        self.bids.first()
+    }
+
+    #[must_use]
+    pub fn best_offer(&self) -> Option<&Level> {
+        self.offers.first()
+    }
+}
+
+/// Order books of tracked instruments.
+#[derive(Debug, Default)]
+pub struct OrderBooks {
+    books: HashMap<u32, OrderBook>,
+}
+
+impl OrderBooks {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start maintaining the instrument's book.
+    pub fn track(&mut self, security_id: u32) -> &mut OrderBook {
+        self.books
+            .entry(security_id)
+            .or_insert_with(|| OrderBook::new(security_id))
+    }
+
+    /// Stop maintaining the instrument's book.
+    pub fn untrack(&mut self, security_id: u32) -> Option<OrderBook> {
+        self.books.remove(&security_id)
+    }
+
+    /// Apply a market event to the book of its instrument if tracked.
+    pub fn apply_event(&mut self, event: &MarketEvent) {
+        if let Some(book) = event.security_id().and_then(|id| self.books.get_mut(&id)) {
+            book.apply_event(event);
+        }
+    }
+
+    /// Apply a status transition to the book of its instrument if tracked.
+    pub fn apply_status(&mut self, transition: &StatusTransition) {
+        if let Some(book) = self.books.get_mut(&transition.security_id) {
+            book.apply_status(transition);
+        }
+    }
+
+    #[must_use]
+    pub fn get(&self, security_id: u32) -> Option<&OrderBook> {
+        self.books.get(&security_id)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &OrderBook> {
+        self.books.values()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn update(action: UpdateAction, side: Side, level: u32, price: i64) -> BookUpdate {
+        BookUpdate {
+            security_id: 1,
+            rpt_seq: None,
+            action,
+            side,
+            implied: false,
+            level: Some(level),
+            price: Some(Decimal::new(-2, price)),
+            size: Some(1),
+            time: None,
+            sending_time: None,
+        }
+    }
+
+    #[test]
+    fn test_levels() {
+        let mut book = OrderBook::new(1);
+        book.apply(&update(UpdateAction::New, Side::Bid, 1, 100));
+        book.apply(&update(UpdateAction::New, Side::Bid, 1, 101));
+        book.apply(&update(UpdateAction::New, Side::Bid, 3, 99));
+        book.apply(&update(UpdateAction::New, Side::Offer, 1, 102));
+        let prices = |levels: &[Level]| levels.iter().map(|l| l.price.mantissa).collect::<Vec<_>>();
+        assert_eq!(prices(book.bids()), vec![101, 100, 99]);
+
+        book.apply(&update(UpdateAction::Change, Side::Bid, 2, 98));
+        assert_eq!(prices(book.bids()), vec![101, 98, 99]);
+        book.apply(&update(UpdateAction::Delete, Side::Bid, 1, 0));
+        assert_eq!(prices(book.bids()), vec![98, 99]);
+        book.apply(&update(UpdateAction::DeleteFrom, Side::Bid, 1, 0));
+        assert_eq!(prices(book.bids()), vec![99]);
+        book.apply(&update(UpdateAction::DeleteThru, Side::Bid, 1, 0));
+        assert!(book.bids().is_empty());
+        assert_eq!(book.best_offer().unwrap().price, Decimal::new(-2, 102));
+    }
+
+    #[test]
+    fn test_tradable() {
+        let mut book = OrderBook::new(1);
+        assert!(book.is_tradable());
+        book.set_status(TradingStatus::Halt);
+        assert!(!book.is_tradable());
+        book.set_status(TradingStatus::NoChange);
+        assert_eq!(book.status(), Some(TradingStatus::Halt));
+        book.set_status(TradingStatus::ReadyToTrade);
+        assert!(book.is_tradable());
+    }
+}
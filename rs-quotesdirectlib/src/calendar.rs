@@ -0,0 +1,213 @@
+//! # Trading calendar
+//!
+//! Answers schedule questions from trading sessions published in
+//! `SecurityDefinition.TradingSessions`, so holidays and early closes reflected
+//! in security definitions need not be hard-coded.
+//!
+//! Session times are UTC timestamps, so sessions spanning midnight and several sessions
+//! per trade date need no special treatment.
+//!
+//! # Examples
+//!
+//! ```rust,ignore
+//! use chrono::Utc;
+//! use quotesdirectlib::calendar::Calendar;
+//!
+//! let mut calendar = Calendar::new();
+//! calendar.add_definition(&definition);
+//! let now = Utc::now();
+//! if calendar.is_open(security_id, now) {
+//!     println!("closes in {:?}", calendar.time_to_close(security_id, now));
+//! } else {
+//!     println!("opens at {:?}", calendar.next_open(security_id, now));
+//! }
+//! ```
+//!
+use chrono::{DateTime, NaiveDate, TimeDelta, Utc};
+use std::collections::HashMap;
+
+use crate::fast::{SecurityDefinition, TradingSession};
+use crate::time::{local_mkt_date, utc_timestamp};
+
+/// Trading session.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub struct Session {
+    pub trade_date: NaiveDate,
+    /// Start of the session including pre-open.
+    pub start: DateTime<Utc>,
+    /// Start of continuous trading.
+    pub open: DateTime<Utc>,
+    /// End of continuous trading.
+    pub close: DateTime<Utc>,
+    /// End of the session including post-close.
+    pub end: DateTime<Utc>,
+}
+
+impl Session {
+    /// Convert session times. Returns `None` if any of the dates is malformed.
+    #[must_use]
+    pub fn from_fix(session: &TradingSession) -> Option<Self> {
+        Some(Self {
+            trade_date: local_mkt_date(session.trade_date)?,
+            start: utc_timestamp(session.trad_ses_start_time)?,
+            open: utc_timestamp(session.trad_ses_open_time)?,
+            close: utc_timestamp(session.trad_ses_close_time)?,
+            end: utc_timestamp(session.trad_ses_end_time)?,
+        })
+    }
+
+    /// The time is between the session start and end.
+    #[must_use]
+    pub fn contains(&self, ts: DateTime<Utc>) -> bool {
+        self.start <= ts && ts < self.end
+    }
+
+    /// The time is between the session open and close.
+    #[must_use]
+    pub fn is_open_at(&self, ts: DateTime<Utc>) -> bool {
+        self.open <= ts && ts < self.close
+    }
+}
+
+/// Trading sessions of instruments.
+#[derive(Debug, Default)]
+pub struct Calendar {
+    sessions: HashMap<u32, Vec<Session>>,
+}
+
+impl Calendar {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set trading sessions of the instrument from its definition.
+    pub fn add_definition(&mut self, definition: &SecurityDefinition) {
+        self.set_sessions(definition.security_id, &definition.trading_sessions);
+    }
+
+    /// Set trading sessions of the instrument, replacing previously known ones.
+    pub fn set_sessions(&mut self, security_id: u32, sessions: &[TradingSession]) {
+        let mut sessions: Vec<_> = sessions.iter().filter_map(Session::from_fix).collect();
+        sessions.sort_by_key(|s| s.start);
+        self.sessions.insert(security_id, sessions);
+    }
+
+    /// Known sessions of the instrument ordered by start time.
+    #[must_use]
+    pub fn sessions(&self, security_id: u32) -> &[Session] {
+        self.sessions.get(&security_id).map_or(&[], Vec::as_slice)
+    }
+
+    /// Session the time falls into.
+    #[must_use]
+    pub fn session_at(&self, security_id: u32, ts: DateTime<Utc>) -> Option<&Session> {
+        self.sessions(security_id).iter().find(|s| s.contains(ts))
+    }
+
+    /// The instrument is in continuous trading at the time.
+    #[must_use]
+    pub fn is_open(&self, security_id: u32, ts: DateTime<Utc>) -> bool {
+        self.sessions(security_id).iter().any(|s| s.is_open_at(ts))
+    }
+
+    /// Next open after the time.
+    #[must_use]
+    pub fn next_open(&self, security_id: u32, ts: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        self.sessions(security_id)
+            .iter()
+            .map(|s| s.open)
+            .filter(|open| *open > ts)
+            .min()
+    }
+
+    /// Trade date at the time: date of the current session or, between sessions, of the next one.
+    #[must_use]
+    pub fn trade_date(&self, security_id: u32, ts: DateTime<Utc>) -> Option<NaiveDate> {
+        let sessions = self.sessions(security_id);
+        sessions
+            .iter()
+            .find(|s| s.contains(ts))
+            .or_else(|| sessions.iter().find(|s| s.start > ts))
+            .map(|s| s.trade_date)
+    }
+
+    /// Time left until close if the instrument is open at the time.
+    #[must_use]
+    pub fn time_to_close(&self, security_id: u32, ts: DateTime<Utc>) -> Option<TimeDelta> {
+        self.sessions(security_id)
+            .iter()
+            .find(|s| s.is_open_at(ts))
+            .map(|s| s.close - ts)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn session(trade_date: u64, start: u64, open: u64, close: u64, end: u64) -> TradingSession {
+        TradingSession {
+            trade_date,
+            trad_ses_start_time: start,
+            trad_ses_open_time: open,
+            trad_ses_close_time: close,
+            trad_ses_end_time: end,
+        }
+    }
+
+    fn ts(value: u64) -> DateTime<Utc> {
+        utc_timestamp(value).unwrap()
+    }
+
+    fn calendar() -> Calendar {
+        let mut calendar = Calendar::new();
+        // Friday session spanning midnight, no Monday session (holiday), Tuesday session
+        calendar.set_sessions(
+            1,
+            &[
+                session(
+                    20250624,
+                    20250623210000000,
+                    20250623220000000,
+                    20250624210000000,
+                    20250624211500000,
+                ),
+                session(
+                    20250620,
+                    20250619210000000,
+                    20250619220000000,
+                    20250620210000000,
+                    20250620211500000,
+                ),
+            ],
+        );
+        calendar
+    }
+
+    #[test]
+    fn test_calendar() {
+        let calendar = calendar();
+        assert!(calendar.is_open(1, ts(20250620000000000)));
+        assert!(!calendar.is_open(1, ts(20250619213000000)));
+        assert_eq!(
+            calendar.time_to_close(1, ts(20250620200000000)),
+            Some(TimeDelta::hours(1))
+        );
+        assert_eq!(
+            calendar.trade_date(1, ts(20250619213000000)),
+            NaiveDate::from_ymd_opt(2025, 6, 20)
+        );
+
+        // over the weekend and holiday
+        let weekend = ts(20250621120000000);
+        assert!(!calendar.is_open(1, weekend));
+        assert_eq!(calendar.time_to_close(1, weekend), None);
+        assert_eq!(calendar.next_open(1, weekend), Some(ts(20250623220000000)));
+        assert_eq!(
+            calendar.trade_date(1, weekend),
+            NaiveDate::from_ymd_opt(2025, 6, 24)
+        );
+        assert_eq!(calendar.next_open(2, weekend), None);
+    }
+}
@@ -0,0 +1,370 @@
+//! # CFI codes
+//!
+//! Parses ISO 10962 *Classification of Financial Instruments* codes
+//! (`SecurityDefinition.CFICode`, `Leg.LegCFICode`) into a structured classification.
+//! Attributes are decoded for futures and listed options, other categories keep raw letters.
+//! Malformed codes are classified as [`Category::Unknown`].
+//!
+//! # Examples
+//!
+//! ```rust,ignore
+//! use quotesdirectlib::cfi::{Category, Delivery};
+//!
+//! let cfi = definition.cfi();
+//! if cfi.category == Category::Future && cfi.delivery() == Some(Delivery::Cash) {
+//!     println!("cash settled future");
+//! }
+//! let calls: Vec<_> = master.filter_cfi(|cfi| cfi.is_call()).collect();
+//! ```
+//!
+use crate::fast::SecurityDefinition;
+use crate::options::OptionRight;
+use crate::secmaster::SecurityMaster;
+
+/// Category (1st letter).
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum Category {
+    Equity,
+    CollectiveInvestment,
+    Debt,
+    Entitlement,
+    ListedOption,
+    Future,
+    Swap,
+    NonListedOption,
+    Spot,
+    Forward,
+    Strategy,
+    Financing,
+    Referential,
+    Other,
+    Unknown,
+}
+
+impl Category {
+    fn from_letter(letter: u8) -> Self {
+        match letter {
+            b'E' => Category::Equity,
+            b'C' => Category::CollectiveInvestment,
+            b'D' => Category::Debt,
+            b'R' => Category::Entitlement,
+            b'O' => Category::ListedOption,
+            b'F' => Category::Future,
+            b'S' => Category::Swap,
+            b'H' => Category::NonListedOption,
+            b'I' => Category::Spot,
+            b'J' => Category::Forward,
+            b'K' => Category::Strategy,
+            b'L' => Category::Financing,
+            b'T' => Category::Referential,
+            b'M' => Category::Other,
+            _ => Category::Unknown,
+        }
+    }
+}
+
+/// Group (2nd letter) of futures and listed options, raw letter of other categories.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum Group {
+    FinancialFuture,
+    CommodityFuture,
+    Call,
+    Put,
+    Other(char),
+}
+
+/// Underlying asset of futures and listed options.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum Underlying {
+    Basket,
+    Equity,
+    Debt,
+    Currency,
+    Index,
+    Option,
+    Future,
+    Swap,
+    InterestRate,
+    Dividend,
+    Commodity,
+    Other,
+    NotApplicable,
+}
+
+impl Underlying {
+    fn from_letter(letter: u8) -> Self {
+        match letter {
+            b'B' => Underlying::Basket,
+            b'S' => Underlying::Equity,
+            b'D' => Underlying::Debt,
+            b'C' => Underlying::Currency,
+            b'I' => Underlying::Index,
+            b'O' => Underlying::Option,
+            b'F' => Underlying::Future,
+            b'W' => Underlying::Swap,
+            b'N' => Underlying::InterestRate,
+            b'V' => Underlying::Dividend,
+            b'T' => Underlying::Commodity,
+            b'X' => Underlying::NotApplicable,
+            _ => Underlying::Other,
+        }
+    }
+}
+
+/// Delivery method.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum Delivery {
+    Physical,
+    Cash,
+    NonDeliverable,
+    /// Physical or cash, elected at exercise.
+    ElectAtExercise,
+    NotApplicable,
+}
+
+impl Delivery {
+    fn from_letter(letter: u8) -> Self {
+        match letter {
+            b'P' => Delivery::Physical,
+            b'C' => Delivery::Cash,
+            b'N' => Delivery::NonDeliverable,
+            b'E' => Delivery::ElectAtExercise,
+            _ => Delivery::NotApplicable,
+        }
+    }
+}
+
+/// Option exercise style.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum ExerciseStyle {
+    European,
+    American,
+    Bermudan,
+    Other,
+}
+
+impl ExerciseStyle {
+    fn from_letter(letter: u8) -> Self {
+        match letter {
+            b'E' => ExerciseStyle::European,
+            b'A' => ExerciseStyle::American,
+            b'B' => ExerciseStyle::Bermudan,
+            _ => ExerciseStyle::Other,
+        }
+    }
+}
+
+/// Attributes (3rd to 6th letters).
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum Attributes {
+    Future {
+        underlying: Underlying,
+        delivery: Delivery,
+        /// `None` if not applicable.
+        standardized: Option<bool>,
+    },
+    Option {
+        exercise: ExerciseStyle,
+        underlying: Underlying,
+        delivery: Delivery,
+        /// `None` if not applicable.
+        standardized: Option<bool>,
+    },
+    Other([char; 4]),
+}
+
+/// Parsed CFI code.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub struct Cfi {
+    pub category: Category,
+    pub group: Group,
+    pub attributes: Attributes,
+}
+
+impl Cfi {
+    /// Parse a CFI code. Codes that are not six uppercase letters are classified as unknown.
+    #[must_use]
+    pub fn parse(code: &str) -> Self {
+        let Ok(letters) = <[u8; 6]>::try_from(code.as_bytes()) else {
+            return Self::unknown(*b"XXXX");
+        };
+        if !letters.iter().all(u8::is_ascii_uppercase) {
+            return Self::unknown(*b"XXXX");
+        }
+        let [category, group, a1, a2, a3, a4] = letters;
+        let category = Category::from_letter(category);
+        match (category, group) {
+            (Category::Future, b'F' | b'C') => Self {
+                category,
+                group: if group == b'F' {
+                    Group::FinancialFuture
+                } else {
+                    Group::CommodityFuture
+                },
+                attributes: Attributes::Future {
+                    // commodity futures classify the commodity by the 3rd letter
+                    underlying: if group == b'F' {
+                        Underlying::from_letter(a1)
+                    } else {
+                        Underlying::Commodity
+                    },
+                    delivery: Delivery::from_letter(a2),
+                    standardized: standardized(a3),
+                },
+            },
+            (Category::ListedOption, b'C' | b'P' | b'M') => Self {
+                category,
+                group: match group {
+                    b'C' => Group::Call,
+                    b'P' => Group::Put,
+                    _ => Group::Other('M'),
+                },
+                attributes: Attributes::Option {
+                    exercise: ExerciseStyle::from_letter(a1),
+                    underlying: Underlying::from_letter(a2),
+                    delivery: Delivery::from_letter(a3),
+                    standardized: standardized(a4),
+                },
+            },
+            (Category::Unknown, _) => Self::unknown([a1, a2, a3, a4]),
+            _ => Self {
+                category,
+                group: Group::Other(char::from(group)),
+                attributes: Attributes::Other([a1, a2, a3, a4].map(char::from)),
+            },
+        }
+    }
+
+    fn unknown(attributes: [u8; 4]) -> Self {
+        Self {
+            category: Category::Unknown,
+            group: Group::Other('X'),
+            attributes: Attributes::Other(attributes.map(char::from)),
+        }
+    }
+
+    #[must_use]
+    pub fn is_future(&self) -> bool {
+        self.category == Category::Future
+    }
+
+    #[must_use]
+    pub fn is_option(&self) -> bool {
+        self.category == Category::ListedOption
+    }
+
+    #[must_use]
+    pub fn is_call(&self) -> bool {
+        self.group == Group::Call
+    }
+
+    #[must_use]
+    pub fn is_put(&self) -> bool {
+        self.group == Group::Put
+    }
+
+    /// Call or put of a listed option.
+    #[must_use]
+    pub fn option_right(&self) -> Option<OptionRight> {
+        match self.group {
+            Group::Call => Some(OptionRight::Call),
+            Group::Put => Some(OptionRight::Put),
+            _ => None,
+        }
+    }
+
+    /// Underlying of a future or listed option.
+    #[must_use]
+    pub fn underlying(&self) -> Option<Underlying> {
+        match self.attributes {
+            Attributes::Future { underlying, .. } | Attributes::Option { underlying, .. } => {
+                Some(underlying)
+            }
+            Attributes::Other(_) => None,
+        }
+    }
+
+    /// Delivery method of a future or listed option.
+    #[must_use]
+    pub fn delivery(&self) -> Option<Delivery> {
+        match self.attributes {
+            Attributes::Future { delivery, .. } | Attributes::Option { delivery, .. } => {
+                Some(delivery)
+            }
+            Attributes::Other(_) => None,
+        }
+    }
+
+    /// Exercise style of a listed option.
+    #[must_use]
+    pub fn exercise_style(&self) -> Option<ExerciseStyle> {
+        match self.attributes {
+            Attributes::Option { exercise, .. } => Some(exercise),
+            _ => None,
+        }
+    }
+}
+
+fn standardized(letter: u8) -> Option<bool> {
+    match letter {
+        b'S' => Some(true),
+        b'N' => Some(false),
+        _ => None,
+    }
+}
+
+impl SecurityMaster {
+    /// Definitions with classification matching the predicate.
+    pub fn filter_cfi<P>(&self, predicate: P) -> impl Iterator<Item = &SecurityDefinition>
+    where
+        P: Fn(&Cfi) -> bool,
+    {
+        self.iter().filter(move |sd| predicate(&sd.cfi()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_future() {
+        let cfi = Cfi::parse("FFICSX");
+        assert_eq!(cfi.category, Category::Future);
+        assert_eq!(cfi.group, Group::FinancialFuture);
+        assert_eq!(
+            cfi.attributes,
+            Attributes::Future {
+                underlying: Underlying::Index,
+                delivery: Delivery::Cash,
+                standardized: Some(true)
+            }
+        );
+        let cfi = Cfi::parse("FCEPSX");
+        assert_eq!(cfi.group, Group::CommodityFuture);
+        assert_eq!(cfi.underlying(), Some(Underlying::Commodity));
+        assert_eq!(cfi.delivery(), Some(Delivery::Physical));
+        assert_eq!(cfi.option_right(), None);
+    }
+
+    #[test]
+    fn test_option() {
+        let cfi = Cfi::parse("OPAFPS");
+        assert!(cfi.is_option() && cfi.is_put());
+        assert_eq!(cfi.option_right(), Some(OptionRight::Put));
+        assert_eq!(cfi.exercise_style(), Some(ExerciseStyle::American));
+        assert_eq!(cfi.underlying(), Some(Underlying::Future));
+        assert_eq!(cfi.delivery(), Some(Delivery::Physical));
+    }
+
+    #[test]
+    fn test_other() {
+        let cfi = Cfi::parse("KMXXXX");
+        assert_eq!(cfi.category, Category::Strategy);
+        assert_eq!(cfi.group, Group::Other('M'));
+        assert_eq!(cfi.delivery(), None);
+        assert_eq!(Cfi::parse("FFI").category, Category::Unknown);
+        assert_eq!(Cfi::parse("ffixsx").category, Category::Unknown);
+        assert_eq!(Cfi::parse("ZZZZZZ").category, Category::Unknown);
+    }
+}
@@ -0,0 +1,474 @@
+//! # Continuous contracts
+//!
+//! Resolves the front and most active futures contract of a product (`SecurityGroup`)
+//! from security definitions and reports rolls when `MostActiveFlag` moves to another contract.
+//!
+//! A definition received live is sent when its `MostActiveFlag` changes, so the roll is timed
+//! by its `SendingTime` or the time it was received ([`ContinuousResolver::process_live`],
+//! [`ContinuousResolver::add_definition_at`]).
+//! Definitions downloaded at once say nothing about when the market rolled; such rolls
+//! are timed by the [lifecycle](crate::lifecycle) of the contracts:
+//! the last trade time of the previous contract, or the activation time of the new one.
+//!
+//! Price series recorded across rolls can be back-adjusted so that the continuous series
+//! has no gaps at roll points: prices before a roll are shifted by the difference
+//! (or scaled by the ratio) between the new and the old contract at the roll time.
+//!
+//! # Examples
+//!
+//! ```rust,ignore
+//! use quotesdirectlib::continuous::{Adjustment, ContinuousResolver, PricePoint, back_adjust};
+//!
+//! let mut resolver = ContinuousResolver::new();
+//! let mut rolls = Vec::new();
+//! for msg in recording {
+//!     rolls.extend(resolver.process(&msg));
+//!     points.extend(tape.process(&msg).iter().filter_map(PricePoint::from_trade));
+//! }
+//! let series = back_adjust(&points, &rolls, Adjustment::Difference);
+//! ```
+//!
+use chrono::{DateTime, Utc};
+use std::collections::{BTreeSet, HashMap};
+
+use crate::bars::Bar;
+use crate::fast::{Message, SecurityDefinition};
+use crate::time::utc_timestamp;
+use crate::trades::Trade;
+
+/// Most active contract of a product changed.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct RollEvent {
+    pub security_group: String,
+    /// Previous most active contract, `None` for the first one seen.
+    pub from: Option<u32>,
+    pub to: u32,
+    /// Time the flag changed for definitions received live. Otherwise the last trade time
+    /// of the previous contract, the activation time of the new contract if unknown.
+    pub time: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Default)]
+struct Product {
+    /// Contracts by `MaturityMonthYear` and `SecurityID`.
+    contracts: BTreeSet<(u64, u32)>,
+    most_active: Option<u32>,
+}
+
+#[derive(Debug)]
+struct Contract {
+    security_group: String,
+    maturity: u64,
+    expiration: Option<DateTime<Utc>>,
+    last_trade: Option<DateTime<Utc>>,
+}
+
+/// Front and most active futures contracts by `SecurityGroup`.
+#[derive(Debug, Default)]
+pub struct ContinuousResolver {
+    products: HashMap<String, Product>,
+    contracts: HashMap<u32, Contract>,
+}
+
+impl ContinuousResolver {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add security definitions from a message of a bulk download, e.g. a recording
+    /// of the definitions channel. Rolls are timed by the contracts' lifecycle.
+    pub fn process(&mut self, msg: &Message) -> Option<RollEvent> {
+        match msg {
+            Message::MDSecurityDefinition(m) => self.add_definition(m),
+            _ => None,
+        }
+    }
+
+    /// Add security definitions from a message received live.
+    /// Rolls are timed by the message's `SendingTime`.
+    pub fn process_live(&mut self, msg: &Message) -> Option<RollEvent> {
+        match msg {
+            Message::MDSecurityDefinition(m) => {
+                self.add(m, utc_timestamp(m.msg_header.sending_time))
+            }
+            _ => None,
+        }
+    }
+
+    /// Add or update a futures contract. Returns a roll if the contract became most active.
+    /// Definitions of other instruments or lacking `SecurityGroup` or `MaturityMonthYear` are ignored.
+    ///
+    /// The most active contract is kept until another contract of the product is flagged
+    /// or it is removed. Rolls are timed by the contracts' lifecycle,
+    /// see [`add_definition_at`](Self::add_definition_at) for definitions received live.
+    pub fn add_definition(&mut self, definition: &SecurityDefinition) -> Option<RollEvent> {
+        self.add(definition, None)
+    }
+
+    /// Add or update a futures contract received live at `time`, e.g. its `SendingTime`
+    /// or receive time, which is the time of the roll.
+    pub fn add_definition_at(
+        &mut self,
+        definition: &SecurityDefinition,
+        time: DateTime<Utc>,
+    ) -> Option<RollEvent> {
+        self.add(definition, Some(time))
+    }
+
+    fn add(
+        &mut self,
+        definition: &SecurityDefinition,
+        time: Option<DateTime<Utc>>,
+    ) -> Option<RollEvent> {
+        let (true, Some(group), Some(maturity)) = (
+            definition.cfi().is_future(),
+            &definition.security_group,
+            definition.maturity_month_year,
+        ) else {
+            return None;
+        };
+        let security_id = definition.security_id;
+        self.remove_contract(security_id);
+        self.contracts.insert(
+            security_id,
+            Contract {
+                security_group: group.clone(),
+                maturity,
+                expiration: definition.expiration_time(),
+                last_trade: definition.last_trade_time(),
+            },
+        );
+        let product = self.products.entry(group.clone()).or_default();
+        product.contracts.insert((maturity, security_id));
+
+        let flagged = definition.most_active_flag.as_deref() == Some("Y");
+        if !flagged || product.most_active == Some(security_id) {
+            return None;
+        }
+        let from = product.most_active.replace(security_id);
+        let time = time.or_else(|| {
+            from.and_then(|id| self.contracts.get(&id)?.last_trade)
+                .or_else(|| definition.activation_time())
+        });
+        Some(RollEvent {
+            security_group: group.clone(),
+            from,
+            to: security_id,
+            time,
+        })
+    }
+
+    /// Remove a contract, e.g. once it expired.
+    pub fn remove(&mut self, security_id: u32) -> bool {
+        let Some(group) = self.remove_contract(security_id) else {
+            return false;
+        };
+        if let Some(product) = self.products.get_mut(&group) {
+            if product.most_active == Some(security_id) {
+                product.most_active = None;
+            }
+            if product.contracts.is_empty() {
+                self.products.remove(&group);
+            }
+        }
+        true
+    }
+
+    /// Contract with the nearest maturity.
+    #[must_use]
+    pub fn front(&self, security_group: &str) -> Option<u32> {
+        self.contracts(security_group).next()
+    }
+
+    /// Contract with the nearest maturity that has not expired at the time.
+    /// See [`SecurityDefinition::expiration_time`].
+    #[must_use]
+    pub fn front_at(&self, security_group: &str, time: DateTime<Utc>) -> Option<u32> {
+        self.contracts(security_group).find(|id| {
+            self.contracts
+                .get(id)
+                .and_then(|c| c.expiration)
+                .is_none_or(|expiration| time < expiration)
+        })
+    }
+
+    /// Contract flagged most active.
+    #[must_use]
+    pub fn most_active(&self, security_group: &str) -> Option<u32> {
+        self.products.get(security_group)?.most_active
+    }
+
+    /// Most active contract, the front contract if none is flagged.
+    #[must_use]
+    pub fn active(&self, security_group: &str) -> Option<u32> {
+        self.most_active(security_group)
+            .or_else(|| self.front(security_group))
+    }
+
+    /// Contracts of the product ordered by maturity.
+    pub fn contracts(&self, security_group: &str) -> impl Iterator<Item = u32> {
+        self.products
+            .get(security_group)
+            .into_iter()
+            .flat_map(|product| product.contracts.iter().map(|(_, id)| *id))
+    }
+
+    /// Known products.
+    pub fn groups(&self) -> impl Iterator<Item = &str> {
+        self.products.keys().map(String::as_str)
+    }
+
+    /// Remove the contract from its product, keeping the most active flag.
+    fn remove_contract(&mut self, security_id: u32) -> Option<String> {
+        let contract = self.contracts.remove(&security_id)?;
+        if let Some(product) = self.products.get_mut(&contract.security_group) {
+            product.contracts.remove(&(contract.maturity, security_id));
+        }
+        Some(contract.security_group)
+    }
+}
+
+/// Price of a contract at a time.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct PricePoint {
+    pub time: DateTime<Utc>,
+    pub security_id: u32,
+    pub price: f64,
+}
+
+impl PricePoint {
+    /// Trade price, `None` if the trade has no time.
+    #[must_use]
+    pub fn from_trade(trade: &Trade) -> Option<Self> {
+        Some(Self {
+            time: trade.time?,
+            security_id: trade.security_id,
+            price: f64::from(trade.price.clone()),
+        })
+    }
+
+    /// Bar close at the bar end.
+    #[must_use]
+    pub fn from_bar(bar: &Bar) -> Self {
+        Self {
+            time: bar.end,
+            security_id: bar.security_id,
+            price: f64::from(bar.close.clone()),
+        }
+    }
+}
+
+/// Back-adjustment method.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub enum Adjustment {
+    /// Add the price difference between the new and the old contract.
+    #[default]
+    Difference,
+    /// Multiply by the price ratio of the new and the old contract.
+    Ratio,
+    /// Splice contracts without adjusting prices.
+    None,
+}
+
+/// Build a back-adjusted continuous series from prices of several contracts.
+///
+/// Before the first roll the series follows the roll's `from` contract, after each roll
+/// its `to` contract; prices of other contracts are dropped. The adjustment of a roll is
+/// computed from the last price of each contract at or before the roll time
+/// and is skipped if either price is unknown. Rolls without time are ignored.
+#[must_use]
+pub fn back_adjust(
+    points: &[PricePoint],
+    rolls: &[RollEvent],
+    adjustment: Adjustment,
+) -> Vec<PricePoint> {
+    let mut points = points.to_vec();
+    points.sort_by_key(|p| p.time);
+    let mut rolls: Vec<_> = rolls
+        .iter()
+        .filter_map(|roll| Some((roll.time?, roll)))
+        .collect();
+    rolls.sort_by_key(|(time, _)| *time);
+
+    let last_price = |security_id: Option<u32>, time: DateTime<Utc>| {
+        points
+            .iter()
+            .take_while(|p| p.time <= time)
+            .filter(|p| Some(p.security_id) == security_id)
+            .last()
+            .map(|p| p.price)
+    };
+    // adjustment of each roll as (difference, ratio)
+    let adjustments: Vec<_> = rolls
+        .iter()
+        .map(|(time, roll)| {
+            match (
+                last_price(roll.from, *time),
+                last_price(Some(roll.to), *time),
+            ) {
+                (Some(old), Some(new)) if old != 0.0 => (new - old, new / old),
+                _ => (0.0, 1.0),
+            }
+        })
+        .collect();
+
+    let mut series = Vec::new();
+    for point in &points {
+        // rolls at or before the point
+        let index = rolls.partition_point(|(time, _)| *time <= point.time);
+        let active = match index {
+            0 => rolls.first().and_then(|(_, roll)| roll.from),
+            _ => Some(rolls[index - 1].1.to),
+        };
+        if !rolls.is_empty() && active != Some(point.security_id) {
+            continue;
+        }
+        let later = &adjustments[index..];
+        let price = match adjustment {
+            Adjustment::Difference => point.price + later.iter().map(|(d, _)| d).sum::<f64>(),
+            Adjustment::Ratio => point.price * later.iter().map(|(_, r)| r).product::<f64>(),
+            Adjustment::None => point.price,
+        };
+        series.push(PricePoint { price, ..*point });
+    }
+    series
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::fast::{Event, fixtures};
+    use chrono::TimeZone;
+
+    fn definition(security_id: u32, maturity: u64, most_active: bool) -> SecurityDefinition {
+        // activated at 1:00, last trade at 2:00 on the maturity month's 20th
+        let events = vec![
+            Event {
+                event_type: 5,
+                event_date: maturity * 100 + 20,
+                event_time: (maturity * 100 + 20) * 1_000_000_000 + 10_000_000,
+            },
+            Event {
+                event_type: 7,
+                event_date: maturity * 100 + 20,
+                event_time: (maturity * 100 + 20) * 1_000_000_000 + 20_000_000,
+            },
+        ];
+        SecurityDefinition {
+            events: Some(events),
+            security_group: Some("EP".to_string()),
+            maturity_month_year: Some(maturity),
+            most_active_flag: Some(if most_active { "Y" } else { "N" }.to_string()),
+            ..fixtures::definition(security_id)
+        }
+    }
+
+    fn time(hour: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2025, 6, 20, hour, 0, 0).unwrap()
+    }
+
+    fn point(hour: u32, security_id: u32, price: f64) -> PricePoint {
+        PricePoint {
+            time: time(hour),
+            security_id,
+            price,
+        }
+    }
+
+    #[test]
+    fn test_resolver() {
+        let mut resolver = ContinuousResolver::new();
+        assert_eq!(resolver.add_definition(&definition(2, 202512, false)), None);
+        assert_eq!(resolver.active("EP"), Some(2));
+        let roll = resolver
+            .add_definition(&definition(1, 202509, true))
+            .unwrap();
+        assert_eq!((roll.from, roll.to), (None, 1));
+        assert_eq!(
+            roll.time,
+            Some(Utc.with_ymd_and_hms(2025, 9, 20, 1, 0, 0).unwrap())
+        );
+        assert_eq!(resolver.add_definition(&definition(1, 202509, true)), None);
+        assert_eq!(resolver.front("EP"), Some(1));
+
+        let roll = resolver
+            .add_definition(&definition(2, 202512, true))
+            .unwrap();
+        assert_eq!((roll.from, roll.to), (Some(1), 2));
+        // timed by the previous contract's last trade, not the definition's sending time
+        assert_eq!(
+            roll.time,
+            Some(Utc.with_ymd_and_hms(2025, 9, 20, 2, 0, 0).unwrap())
+        );
+        assert_eq!(resolver.active("EP"), Some(2));
+        assert_eq!(resolver.contracts("EP").collect::<Vec<_>>(), vec![1, 2]);
+
+        assert!(resolver.remove(1));
+        assert_eq!(resolver.front("EP"), Some(2));
+        assert!(resolver.remove(2));
+        assert_eq!(resolver.active("EP"), None);
+        assert_eq!(resolver.groups().count(), 0);
+    }
+
+    #[test]
+    fn test_live_roll() {
+        let mut resolver = ContinuousResolver::new();
+        resolver.add_definition(&definition(1, 202509, true));
+        resolver.add_definition(&definition(2, 202512, false));
+
+        // flag moved weeks before the previous contract's last trade
+        let mut flagged = definition(2, 202512, true);
+        flagged.msg_header.sending_time = 20250905133000000;
+        let roll = resolver
+            .process_live(&Message::MDSecurityDefinition(flagged.clone()))
+            .unwrap();
+        assert_eq!((roll.from, roll.to), (Some(1), 2));
+        assert_eq!(
+            roll.time,
+            Some(Utc.with_ymd_and_hms(2025, 9, 5, 13, 30, 0).unwrap())
+        );
+
+        flagged.most_active_flag = Some("N".to_string());
+        resolver.add_definition(&flagged);
+        let roll = resolver
+            .add_definition_at(&definition(1, 202509, true), time(12))
+            .unwrap();
+        assert_eq!((roll.from, roll.to), (Some(2), 1));
+        assert_eq!(roll.time, Some(time(12)));
+    }
+
+    #[test]
+    fn test_back_adjust() {
+        let points = [
+            point(1, 1, 100.0),
+            point(2, 2, 104.0),
+            point(3, 1, 101.0),
+            point(3, 2, 106.0),
+            point(4, 2, 107.0),
+            point(5, 1, 102.0),
+        ];
+        let rolls = [RollEvent {
+            security_group: "EP".to_string(),
+            from: Some(1),
+            to: 2,
+            time: Some(time(3)),
+        }];
+        let prices = |adjustment| {
+            back_adjust(&points, &rolls, adjustment)
+                .iter()
+                .map(|p| (p.security_id, p.price))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            prices(Adjustment::Difference),
+            vec![(1, 105.0), (2, 106.0), (2, 107.0)]
+        );
+        assert_eq!(
+            prices(Adjustment::None),
+            vec![(1, 100.0), (2, 106.0), (2, 107.0)]
+        );
+        let ratio = prices(Adjustment::Ratio);
+        assert!((ratio[0].1 - 100.0 * 106.0 / 101.0).abs() < 1e-9);
+    }
+}
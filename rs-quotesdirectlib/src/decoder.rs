@@ -0,0 +1,379 @@
+//! # Message decoder
+//!
+//! FAST decoder with an explicit dictionary reset policy.
+//!
+//! Operators like `<copy/>`, `<delta/>` and `<increment/>` encode fields relative to
+//! the previous value kept in the decoder's dictionaries, so the decoder state must follow
+//! the sender's: Quotes Direct UDP packets are self-contained and dictionaries are reset
+//! at the start of each packet ([`ResetPolicy::PerPacket`]), while TCP streams keep state
+//! for the whole session ([`ResetPolicy::Never`]).
+//! With a per-packet policy a lost packet does not corrupt decoding of the following ones.
+//!
+//! Dictionaries are also reset after `SequenceReset` regardless of the policy.
+//!
+//! Templates are compiled in, or loaded at runtime with [`MessageDecoder::from_file`]
+//! which checks them against the typed messages first.
+//!
+//! A decode error leaves the dictionaries in an unknown state.
+//! [`MessageDecoder::decode_packet_recovering`] discards the rest of the packet, resets
+//! the dictionaries and counts the error, so decoding resumes with the next packet.
+//! This only works with [`ResetPolicy::PerPacket`], where the sender resets its dictionaries too;
+//! a stateful stream cannot be followed after an error and the session must be restarted.
+//! Instruments updated by the lost messages need recovery,
+//! see [`RecoveryTracker`](crate::recovery::RecoveryTracker).
+//!
+//! # Examples
+//!
+//! ```rust,ignore
+//! use quotesdirectlib::decoder::{MessageDecoder, ResetPolicy};
+//!
+//! let mut decoder = MessageDecoder::new(ResetPolicy::PerPacket)?;
+//! for msg in decoder.decode_packet(packet.payload)? {
+//!     println!("{msg:?}");
+//! }
+//! ```
+//!
+use fastlib::Decoder;
+
+use std::path::Path;
+
+use crate::dynamic::{AnyMessage, DynamicFactory, DynamicMessage};
+use crate::fast::{Message, TEMPLATES_XML};
+use crate::templates::TemplateSet;
+use crate::{Error, Result};
+
+/// When to reset the decoder's dictionaries.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub enum ResetPolicy {
+    /// Before the first message of each packet, for UDP feeds.
+    PerPacket,
+    /// Before each message.
+    PerMessage,
+    /// Keep state for the whole stream, for TCP sessions.
+    #[default]
+    Never,
+}
+
+/// Messages of a packet decoded up to the first error.
+#[derive(Debug)]
+pub struct PacketDecode {
+    pub messages: Vec<Message>,
+    pub error: Option<Error>,
+    /// Bytes of the packet discarded after the error.
+    pub discarded: usize,
+}
+
+/// FAST decoder of Quotes Direct messages applying a [`ResetPolicy`].
+pub struct MessageDecoder {
+    decoder: Decoder,
+    policy: ResetPolicy,
+    decode_errors: u64,
+}
+
+impl MessageDecoder {
+    /// Create a decoder for the bundled templates.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the templates cannot be parsed.
+    pub fn new(policy: ResetPolicy) -> Result<Self> {
+        Self::with_templates(TEMPLATES_XML, policy)
+    }
+
+    /// Create a decoder for the templates XML.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the templates cannot be parsed.
+    pub fn with_templates(xml: &str, policy: ResetPolicy) -> Result<Self> {
+        Ok(Self {
+            decoder: Decoder::new_from_xml(xml)?,
+            policy,
+            decode_errors: 0,
+        })
+    }
+
+    /// Create a decoder for templates checked against the typed messages.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the templates cannot be parsed or do not match the messages.
+    pub fn with_template_set(templates: &TemplateSet, policy: ResetPolicy) -> Result<Self> {
+        templates.check()?;
+        Self::with_templates(templates.xml(), policy)
+    }
+
+    /// Create a decoder for templates loaded from a file, see [`MessageDecoder::with_template_set`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read, or the templates cannot be parsed
+    /// or do not match the messages.
+    pub fn from_file(path: &Path, policy: ResetPolicy) -> Result<Self> {
+        Self::with_template_set(&TemplateSet::from_file(path)?, policy)
+    }
+
+    #[must_use]
+    pub fn policy(&self) -> ResetPolicy {
+        self.policy
+    }
+
+    /// Mark the start of a new packet.
+    pub fn start_packet(&mut self) {
+        if self.policy == ResetPolicy::PerPacket {
+            self.decoder.reset();
+        }
+    }
+
+    /// Decode one message from the buffer.
+    /// Returns the message and the number of bytes consumed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the message cannot be decoded.
+    pub fn decode(&mut self, buffer: &[u8]) -> Result<(Message, usize)> {
+        if self.policy == ResetPolicy::PerMessage {
+            self.decoder.reset();
+        }
+        let (msg, size): (Message, u64) = fastlib::from_buffer(&mut self.decoder, buffer)?;
+        if let Message::SequenceReset(_) = msg {
+            // the sender restarts the FAST stream after the reset
+            self.decoder.reset();
+        }
+        // the decoder never consumes more than the buffer holds
+        Ok((msg, usize::try_from(size).unwrap_or(buffer.len())))
+    }
+
+    /// Decode one message from the buffer into a [`DynamicMessage`] of any template.
+    /// Returns the message and the number of bytes consumed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the message cannot be decoded.
+    pub fn decode_dynamic(&mut self, buffer: &[u8]) -> Result<(DynamicMessage, usize)> {
+        if self.policy == ResetPolicy::PerMessage {
+            self.decoder.reset();
+        }
+        let mut factory = DynamicFactory::default();
+        let size = self.decoder.decode_buffer(buffer, &mut factory)?;
+        let msg = factory
+            .take()
+            .ok_or_else(|| Error::DynamicMessage("incomplete message".to_string()))?;
+        if msg.name == "SequenceReset" {
+            // the sender restarts the FAST stream after the reset
+            self.decoder.reset();
+        }
+        Ok((msg, usize::try_from(size).unwrap_or(buffer.len())))
+    }
+
+    /// Decode one message from the buffer, falling back to [`DynamicMessage`]
+    /// if it does not match the typed structs.
+    /// Slower than [`MessageDecoder::decode`], the message is decoded into the dynamic tree first.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the message cannot be decoded.
+    pub fn decode_any(&mut self, buffer: &[u8]) -> Result<(AnyMessage, usize)> {
+        let (msg, size) = self.decode_dynamic(buffer)?;
+        let msg = match msg.to_message() {
+            Ok(typed) => AnyMessage::Typed(typed),
+            Err(_) => AnyMessage::Dynamic(msg),
+        };
+        Ok((msg, size))
+    }
+
+    /// Decode all messages of a packet's payload.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if any message cannot be decoded.
+    pub fn decode_packet(&mut self, payload: &[u8]) -> Result<Vec<Message>> {
+        self.start_packet();
+        let mut messages = Vec::new();
+        let mut offset = 0;
+        while offset < payload.len() {
+            let (msg, size) = self.decode(&payload[offset..])?;
+            messages.push(msg);
+            offset += size;
+        }
+        Ok(messages)
+    }
+
+    /// Decode messages of a packet's payload up to the first error.
+    /// On error the rest of the packet is discarded, see [`MessageDecoder::recover`].
+    pub fn decode_packet_recovering(&mut self, payload: &[u8]) -> PacketDecode {
+        self.start_packet();
+        let mut messages = Vec::new();
+        let mut offset = 0;
+        while offset < payload.len() {
+            match self.decode(&payload[offset..]) {
+                Ok((msg, size)) => {
+                    messages.push(msg);
+                    offset += size;
+                }
+                Err(err) => {
+                    self.recover();
+                    return PacketDecode {
+                        messages,
+                        error: Some(err),
+                        discarded: payload.len() - offset,
+                    };
+                }
+            }
+        }
+        PacketDecode {
+            messages,
+            error: None,
+            discarded: 0,
+        }
+    }
+
+    /// Reset the dictionaries after a decode error and count the error.
+    /// Decoding stays in sync with the sender only if it resets its dictionaries
+    /// at the next packet, i.e. with [`ResetPolicy::PerPacket`].
+    pub fn recover(&mut self) {
+        self.decode_errors += 1;
+        self.decoder.reset();
+    }
+
+    /// Number of decode errors recovered from.
+    #[must_use]
+    pub fn decode_errors(&self) -> u64 {
+        self.decode_errors
+    }
+
+    /// Reset the dictionaries.
+    pub fn reset(&mut self) {
+        self.decoder.reset();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::fast::fixtures::{inc_refresh, md_entry};
+    use fastlib::{Decimal, Encoder};
+
+    /// Packets of two messages each with consecutive `RptSeq` and prices.
+    fn messages() -> Vec<Vec<Message>> {
+        (0..3)
+            .map(|packet| {
+                (0..2)
+                    .map(|i| {
+                        let n = packet * 2 + i + 1;
+                        let price = Decimal::new(-2, 450_000 + i64::from(n) * 25);
+                        inc_refresh(n, vec![md_entry(100, n, Some(price))])
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Encode packets resetting the encoder according to the policy.
+    fn encode(packets: &[Vec<Message>], policy: ResetPolicy) -> Vec<Vec<u8>> {
+        let mut encoder = Encoder::new_from_xml(TEMPLATES_XML).unwrap();
+        packets
+            .iter()
+            .map(|messages| {
+                if policy == ResetPolicy::PerPacket {
+                    encoder.reset();
+                }
+                let mut payload = Vec::new();
+                for msg in messages {
+                    if policy == ResetPolicy::PerMessage {
+                        encoder.reset();
+                    }
+                    payload.extend(fastlib::to_vec(&mut encoder, msg).unwrap());
+                }
+                payload
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_policies() {
+        let packets = messages();
+        for policy in [
+            ResetPolicy::PerPacket,
+            ResetPolicy::PerMessage,
+            ResetPolicy::Never,
+        ] {
+            let mut decoder = MessageDecoder::new(policy).unwrap();
+            for (payload, expected) in encode(&packets, policy).iter().zip(&packets) {
+                assert_eq!(
+                    &decoder.decode_packet(payload).unwrap(),
+                    expected,
+                    "{policy:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_packet_loss() {
+        let packets = messages();
+
+        // self-contained packets decode correctly after the loss
+        let payloads = encode(&packets, ResetPolicy::PerPacket);
+        let mut decoder = MessageDecoder::new(ResetPolicy::PerPacket).unwrap();
+        decoder.decode_packet(&payloads[0]).unwrap();
+        assert_eq!(decoder.decode_packet(&payloads[2]).unwrap(), packets[2]);
+
+        // stateful stream cannot be followed after the loss
+        let payloads = encode(&packets, ResetPolicy::Never);
+        let mut decoder = MessageDecoder::new(ResetPolicy::Never).unwrap();
+        decoder.decode_packet(&payloads[0]).unwrap();
+        let decoded = decoder.decode_packet(&payloads[2]).unwrap();
+        // RptSeq increments and MDEntryPx deltas apply to the values of the first packet
+        let entries = |messages: &[Message]| -> Vec<(u32, Option<Decimal>)> {
+            messages
+                .iter()
+                .map(|msg| {
+                    let Message::MDIncRefresh(m) = msg else {
+                        panic!("expected MDIncRefresh");
+                    };
+                    (m.md_entries[0].rpt_seq, m.md_entries[0].md_entry_px.clone())
+                })
+                .collect()
+        };
+        assert_eq!(
+            entries(&packets[2]),
+            [
+                (5, Some(Decimal::new(-2, 450_125))),
+                (6, Some(Decimal::new(-2, 450_150)))
+            ]
+        );
+        assert_eq!(
+            entries(&decoded),
+            [
+                (3, Some(Decimal::new(-2, 450_075))),
+                (4, Some(Decimal::new(-2, 450_100)))
+            ]
+        );
+    }
+
+    #[test]
+    fn test_recovering() {
+        let packets = messages();
+        let mut payloads = encode(&packets, ResetPolicy::PerPacket);
+        // corrupt the second message of the second packet
+        let first = fastlib::to_vec(
+            &mut Encoder::new_from_xml(TEMPLATES_XML).unwrap(),
+            &packets[1][0],
+        )
+        .unwrap()
+        .len();
+        payloads[1].truncate(first + 2);
+
+        let mut decoder = MessageDecoder::new(ResetPolicy::PerPacket).unwrap();
+        let decoded = decoder.decode_packet_recovering(&payloads[0]);
+        assert!(decoded.error.is_none());
+        let decoded = decoder.decode_packet_recovering(&payloads[1]);
+        assert_eq!(decoded.messages, packets[1][..1]);
+        assert!(decoded.error.is_some());
+        assert_eq!(decoded.discarded, 2);
+        assert_eq!(decoder.decode_errors(), 1);
+        let decoded = decoder.decode_packet_recovering(&payloads[2]);
+        assert_eq!(decoded.messages, packets[2]);
+    }
+}
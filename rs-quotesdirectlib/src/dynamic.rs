@@ -0,0 +1,601 @@
+//! # Dynamic messages
+//!
+//! Schema-less decoding of any FAST message into a tree of named fields.
+//!
+//! Messages of templates the typed [`Message`] enum does not know, or with fields the structs
+//! do not have yet, can still be printed and stored. A dynamic message converts into the typed
+//! [`Message`] when it matches,
+//! see [`MessageDecoder::decode_any`](crate::decoder::MessageDecoder::decode_any).
+//!
+//! Fields of static `templateRef`s are inlined, dynamic `templateRef`s are nested groups
+//! named after the template. Absent optional fields are omitted.
+//!
+//! Dynamic messages serialize like the typed ones, a message decoded either way has the same
+//! serialized form.
+//!
+//! # Examples
+//!
+//! ```rust,ignore
+//! use quotesdirectlib::decoder::{MessageDecoder, ResetPolicy};
+//!
+//! let mut decoder = MessageDecoder::new(ResetPolicy::PerPacket)?;
+//! let (msg, size) = decoder.decode_dynamic(&payload)?;
+//! println!("{}", serde_json::to_string(&msg)?);
+//! if let Some(Value::Sequence(entries)) = msg.fields.get("MDEntries") {
+//!     println!("{} entries", entries.len());
+//! }
+//! ```
+//!
+use fastlib::{Decimal, MessageFactory};
+use serde::de::value::{Error as DeError, MapDeserializer, SeqDeserializer};
+use serde::de::{self, IntoDeserializer, Visitor};
+use serde::ser::{SerializeMap, SerializeSeq};
+use serde::{Serialize, Serializer, forward_to_deserialize_any};
+
+use crate::fast::Message;
+use crate::{Error, Result};
+
+/// Field value.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Value {
+    UInt32(u32),
+    Int32(i32),
+    UInt64(u64),
+    Int64(i64),
+    Decimal(Decimal),
+    String(String),
+    Bytes(Vec<u8>),
+    Group(Fields),
+    Sequence(Vec<Fields>),
+}
+
+/// Named fields in template order.
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct Fields(Vec<(String, Value)>);
+
+impl Fields {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[must_use]
+    pub fn get(&self, name: &str) -> Option<&Value> {
+        self.0.iter().find(|(n, _)| n == name).map(|(_, v)| v)
+    }
+
+    pub fn push(&mut self, name: &str, value: Value) {
+        self.0.push((name.to_string(), value));
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Value)> {
+        self.0.iter().map(|(n, v)| (n.as_str(), v))
+    }
+
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+/// Typed message, or dynamic if it does not match the typed structs.
+#[allow(clippy::large_enum_variant)]
+#[derive(Debug, PartialEq)]
+pub enum AnyMessage {
+    Typed(Message),
+    Dynamic(DynamicMessage),
+}
+
+impl AnyMessage {
+    #[must_use]
+    pub fn typed(&self) -> Option<&Message> {
+        match self {
+            AnyMessage::Typed(msg) => Some(msg),
+            AnyMessage::Dynamic(_) => None,
+        }
+    }
+}
+
+/// Serializes the typed or dynamic message, both have the same form.
+impl Serialize for AnyMessage {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        match self {
+            AnyMessage::Typed(msg) => msg.serialize(serializer),
+            AnyMessage::Dynamic(msg) => msg.serialize(serializer),
+        }
+    }
+}
+
+/// Message decoded without the typed structs.
+#[derive(Debug, PartialEq, Clone)]
+pub struct DynamicMessage {
+    pub template_id: u32,
+    pub name: String,
+    pub fields: Fields,
+}
+
+impl DynamicMessage {
+    /// Convert into the typed message.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the template or its fields do not match the typed message.
+    pub fn to_message(&self) -> Result<Message> {
+        de::Deserialize::deserialize(MessageDeserializer(self))
+            .map_err(|err: DeError| Error::DynamicMessage(format!("{}: {err}", self.name)))
+    }
+}
+
+/// Serializes as the typed messages do: `{"<template name>": {fields}}`.
+impl Serialize for DynamicMessage {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(1))?;
+        map.serialize_entry(&self.name, &self.fields)?;
+        map.end()
+    }
+}
+
+impl Serialize for Fields {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.0.len()))?;
+        for (name, value) in &self.0 {
+            map.serialize_entry(name, value)?;
+        }
+        map.end()
+    }
+}
+
+impl Serialize for Value {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        match self {
+            Value::UInt32(v) => serializer.serialize_u32(*v),
+            Value::Int32(v) => serializer.serialize_i32(*v),
+            Value::UInt64(v) => serializer.serialize_u64(*v),
+            Value::Int64(v) => serializer.serialize_i64(*v),
+            Value::Decimal(v) => v.serialize(serializer),
+            Value::String(v) => serializer.serialize_str(v),
+            Value::Bytes(v) => v.serialize(serializer),
+            Value::Group(v) => v.serialize(serializer),
+            Value::Sequence(items) => {
+                let mut seq = serializer.serialize_seq(Some(items.len()))?;
+                for item in items {
+                    seq.serialize_element(item)?;
+                }
+                seq.end()
+            }
+        }
+    }
+}
+
+/// Exact decimal notation, without the float conversion of `Decimal::to_string`.
+fn decimal_to_string(d: &Decimal) -> String {
+    let digits = d.mantissa.unsigned_abs().to_string();
+    let sign = if d.mantissa < 0 { "-" } else { "" };
+    if d.exponent >= 0 {
+        if d.mantissa == 0 {
+            return "0".to_string();
+        }
+        return format!(
+            "{sign}{digits}{}",
+            "0".repeat(d.exponent.unsigned_abs() as usize)
+        );
+    }
+    let scale = d.exponent.unsigned_abs() as usize;
+    let digits = format!("{digits:0>width$}", width = scale + 1);
+    let (int, frac) = digits.split_at(digits.len() - scale);
+    format!("{sign}{int}.{frac}")
+}
+
+enum Node {
+    Group(String, Fields),
+    Sequence(String, Vec<Fields>),
+    Item(Fields),
+}
+
+/// Builds a [`DynamicMessage`] from the decoder callbacks.
+#[derive(Default)]
+pub(crate) struct DynamicFactory {
+    template_id: u32,
+    stack: Vec<Node>,
+    /// Whether each open `templateRef` is dynamic.
+    refs: Vec<bool>,
+    message: Option<DynamicMessage>,
+}
+
+impl DynamicFactory {
+    pub(crate) fn take(&mut self) -> Option<DynamicMessage> {
+        self.message.take()
+    }
+
+    fn fields(&mut self) -> Option<&mut Fields> {
+        match self.stack.last_mut()? {
+            Node::Group(_, fields) | Node::Item(fields) => Some(fields),
+            Node::Sequence(..) => None,
+        }
+    }
+
+    fn close_group(&mut self) {
+        if let Some(Node::Group(name, group)) = self.stack.pop()
+            && let Some(fields) = self.fields()
+        {
+            fields.push(&name, Value::Group(group));
+        }
+    }
+}
+
+impl MessageFactory for DynamicFactory {
+    fn start_template(&mut self, id: u32, name: &str) {
+        self.template_id = id;
+        self.stack = vec![Node::Group(name.to_string(), Fields::new())];
+        self.refs.clear();
+        self.message = None;
+    }
+
+    fn stop_template(&mut self) {
+        if let Some(Node::Group(name, fields)) = self.stack.pop() {
+            self.message = Some(DynamicMessage {
+                template_id: self.template_id,
+                name,
+                fields,
+            });
+        }
+    }
+
+    fn set_value(&mut self, _id: u32, name: &str, value: Option<fastlib::Value>) {
+        let Some(value) = value else {
+            return;
+        };
+        let value = match value {
+            fastlib::Value::UInt32(v) => Value::UInt32(v),
+            fastlib::Value::Int32(v) => Value::Int32(v),
+            fastlib::Value::UInt64(v) => Value::UInt64(v),
+            fastlib::Value::Int64(v) => Value::Int64(v),
+            fastlib::Value::Decimal(v) => Value::Decimal(v),
+            fastlib::Value::ASCIIString(v) | fastlib::Value::UnicodeString(v) => Value::String(v),
+            fastlib::Value::Bytes(v) => Value::Bytes(v),
+        };
+        if let Some(fields) = self.fields() {
+            fields.push(name, value);
+        }
+    }
+
+    fn start_sequence(&mut self, _id: u32, name: &str, length: u32) {
+        self.stack.push(Node::Sequence(
+            name.to_string(),
+            Vec::with_capacity(length as usize),
+        ));
+    }
+
+    fn start_sequence_item(&mut self, _index: u32) {
+        self.stack.push(Node::Item(Fields::new()));
+    }
+
+    fn stop_sequence_item(&mut self) {
+        if let Some(Node::Item(item)) = self.stack.pop()
+            && let Some(Node::Sequence(_, items)) = self.stack.last_mut()
+        {
+            items.push(item);
+        }
+    }
+
+    fn stop_sequence(&mut self) {
+        if let Some(Node::Sequence(name, items)) = self.stack.pop()
+            && let Some(fields) = self.fields()
+        {
+            fields.push(&name, Value::Sequence(items));
+        }
+    }
+
+    fn start_group(&mut self, name: &str) {
+        self.stack
+            .push(Node::Group(name.to_string(), Fields::new()));
+    }
+
+    fn stop_group(&mut self) {
+        self.close_group();
+    }
+
+    fn start_template_ref(&mut self, name: &str, dynamic: bool) {
+        self.refs.push(dynamic);
+        if dynamic {
+            self.stack
+                .push(Node::Group(name.to_string(), Fields::new()));
+        }
+    }
+
+    fn stop_template_ref(&mut self) {
+        if self.refs.pop() == Some(true) {
+            self.close_group();
+        }
+    }
+}
+
+/// Deserializes the message as the externally tagged [`Message`] enum.
+struct MessageDeserializer<'a>(&'a DynamicMessage);
+
+impl<'de> de::Deserializer<'de> for MessageDeserializer<'_> {
+    type Error = DeError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        visitor.visit_enum(self)
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct newtype_struct seq tuple
+        tuple_struct map struct enum identifier ignored_any
+    }
+}
+
+impl<'de> de::EnumAccess<'de> for MessageDeserializer<'_> {
+    type Error = DeError;
+    type Variant = Self;
+
+    fn variant_seed<V: de::DeserializeSeed<'de>>(
+        self,
+        seed: V,
+    ) -> Result<(V::Value, Self), DeError> {
+        let variant = seed.deserialize(self.0.name.as_str().into_deserializer())?;
+        Ok((variant, self))
+    }
+}
+
+impl<'de> de::VariantAccess<'de> for MessageDeserializer<'_> {
+    type Error = DeError;
+
+    fn unit_variant(self) -> Result<(), DeError> {
+        Err(de::Error::invalid_type(
+            de::Unexpected::NewtypeVariant,
+            &"unit variant",
+        ))
+    }
+
+    fn newtype_variant_seed<T: de::DeserializeSeed<'de>>(
+        self,
+        seed: T,
+    ) -> Result<T::Value, DeError> {
+        seed.deserialize(FieldsDeserializer(&self.0.fields))
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value, DeError> {
+        de::Deserializer::deserialize_any(FieldsDeserializer(&self.0.fields), visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, DeError> {
+        de::Deserializer::deserialize_any(FieldsDeserializer(&self.0.fields), visitor)
+    }
+}
+
+struct FieldsDeserializer<'a>(&'a Fields);
+
+impl<'de> de::Deserializer<'de> for FieldsDeserializer<'_> {
+    type Error = DeError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        let mut map = MapDeserializer::new(
+            self.0
+                .0
+                .iter()
+                .map(|(name, value)| (name.as_str(), ValueDeserializer(value))),
+        );
+        let value = visitor.visit_map(&mut map)?;
+        map.end()?;
+        Ok(value)
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct newtype_struct seq tuple
+        tuple_struct map struct enum identifier ignored_any
+    }
+}
+
+impl IntoDeserializer<'_, DeError> for FieldsDeserializer<'_> {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+struct ValueDeserializer<'a>(&'a Value);
+
+impl<'de> de::Deserializer<'de> for ValueDeserializer<'_> {
+    type Error = DeError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        match self.0 {
+            Value::UInt32(v) => visitor.visit_u32(*v),
+            Value::Int32(v) => visitor.visit_i32(*v),
+            Value::UInt64(v) => visitor.visit_u64(*v),
+            Value::Int64(v) => visitor.visit_i64(*v),
+            Value::Decimal(v) => visitor.visit_string(decimal_to_string(v)),
+            Value::String(v) => visitor.visit_str(v),
+            Value::Bytes(v) => {
+                let mut seq = SeqDeserializer::new(v.iter().copied());
+                let value = visitor.visit_seq(&mut seq)?;
+                seq.end()?;
+                Ok(value)
+            }
+            Value::Group(v) => de::Deserializer::deserialize_any(FieldsDeserializer(v), visitor),
+            Value::Sequence(items) => {
+                let mut seq = SeqDeserializer::new(items.iter().map(FieldsDeserializer));
+                let value = visitor.visit_seq(&mut seq)?;
+                seq.end()?;
+                Ok(value)
+            }
+        }
+    }
+
+    /// Present fields are always `Some`, absent ones are missing from the map.
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        visitor.visit_some(self)
+    }
+
+    /// `Decimal` deserializes from `(exponent, mantissa)`.
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, DeError> {
+        let Value::Decimal(v) = self.0 else {
+            return self.deserialize_any(visitor);
+        };
+        let mut seq = SeqDeserializer::new([i64::from(v.exponent), v.mantissa].into_iter());
+        let value = visitor.visit_seq(&mut seq)?;
+        seq.end()?;
+        Ok(value)
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct newtype_struct seq tuple
+        map struct enum identifier ignored_any
+    }
+}
+
+impl IntoDeserializer<'_, DeError> for ValueDeserializer<'_> {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::decoder::{MessageDecoder, ResetPolicy};
+    use crate::fast::fixtures::{header, inc_refresh, md_entry};
+    use crate::fast::{News, NewsCategory, TEMPLATES_XML};
+    use fastlib::Encoder;
+
+    fn update() -> Message {
+        inc_refresh(1, vec![md_entry(100, 1, Some(Decimal::new(-2, -450_025)))])
+    }
+
+    fn news() -> Message {
+        Message::News(News {
+            message_type: "B".to_string(),
+            msg_header: header(2),
+            message_encoding: "UTF-8".to_string(),
+            appl_id: "1".to_string(),
+            news_id: "N1".to_string(),
+            news_source_id: 7,
+            last_fragment: Some("Y".to_string()),
+            news_ref_ids: None,
+            orig_time: None,
+            urgency: Some("0".to_string()),
+            news_branding: None,
+            accession_number: None,
+            encoded_headline: None,
+            encoded_text: None,
+            news_categories: Some(vec![NewsCategory {
+                category_class: 1,
+                category_code: "EN".to_string(),
+            }]),
+        })
+    }
+
+    fn encode(msg: &Message) -> Vec<u8> {
+        fastlib::to_vec(&mut Encoder::new_from_xml(TEMPLATES_XML).unwrap(), msg).unwrap()
+    }
+
+    #[test]
+    fn test_decode_dynamic() {
+        let raw = encode(&update());
+        let mut decoder = MessageDecoder::new(ResetPolicy::PerMessage).unwrap();
+        let (msg, size) = decoder.decode_dynamic(&raw).unwrap();
+        assert_eq!(size, raw.len());
+        assert_eq!(msg.template_id, 1);
+        assert_eq!(msg.name, "MDIncRefresh");
+        // header is inlined
+        assert_eq!(msg.fields.get("MsgSeqNum"), Some(&Value::UInt32(1)));
+        let Some(Value::Sequence(entries)) = msg.fields.get("MDEntries") else {
+            panic!("no entries");
+        };
+        assert_eq!(
+            entries[0].get("MDEntryPx"),
+            Some(&Value::Decimal(Decimal::new(-2, -450_025)))
+        );
+        // absent optional field
+        assert_eq!(entries[0].get("QuoteCondition"), None);
+
+        assert_eq!(msg.to_message().unwrap(), update());
+        let json = serde_json::to_string(&msg).unwrap();
+        assert!(json.starts_with(
+            r#"{"MDIncRefresh":{"MessageType":"X","ApplVerID":"8","SenderCompID":"CQG","MsgSeqNum":1,"#
+        ));
+        assert!(json.contains(r#""MDEntryPx":[-2,-450025],"#));
+    }
+
+    #[test]
+    fn test_decode_any() {
+        let mut raw = encode(&update());
+        raw.extend(encode(&news()));
+
+        let mut decoder = MessageDecoder::new(ResetPolicy::PerMessage).unwrap();
+        let (msg, size) = decoder.decode_any(&raw).unwrap();
+        assert_eq!(msg.typed(), Some(&update()));
+        let (msg, _) = decoder.decode_any(&raw[size..]).unwrap();
+        assert_eq!(msg.typed(), Some(&news()));
+
+        // byte vectors convert too
+        let (mut msg, _) = decoder.decode_dynamic(&raw[size..]).unwrap();
+        msg.fields
+            .push("EncodedHeadline", Value::Bytes(b"Headline".to_vec()));
+        let Ok(Message::News(news)) = msg.to_message() else {
+            panic!("not converted");
+        };
+        assert_eq!(news.encoded_headline, Some(b"Headline".to_vec()));
+    }
+
+    #[test]
+    fn test_same_json() {
+        for msg in [update(), news()] {
+            let raw = encode(&msg);
+            let mut decoder = MessageDecoder::new(ResetPolicy::PerMessage).unwrap();
+            let (typed, _) = decoder.decode_any(&raw).unwrap();
+            let mut decoder = MessageDecoder::new(ResetPolicy::PerMessage).unwrap();
+            let (dynamic, _) = decoder.decode_dynamic(&raw).unwrap();
+            assert!(matches!(typed, AnyMessage::Typed(_)));
+            assert_eq!(
+                serde_json::to_string(&typed).unwrap(),
+                serde_json::to_string(&AnyMessage::Dynamic(dynamic)).unwrap()
+            );
+        }
+
+        // byte vectors are not encoded from the typed structs
+        assert_eq!(
+            serde_json::to_string(&Value::Bytes(b"Headline".to_vec())).unwrap(),
+            serde_json::to_string(&b"Headline".to_vec()).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_unknown_template() {
+        let msg = DynamicMessage {
+            template_id: 99,
+            name: "NewMessage".to_string(),
+            fields: Fields(vec![
+                ("Text".to_string(), Value::String("a\nb".to_string())),
+                ("Px".to_string(), Value::Decimal(Decimal::new(-3, 5))),
+            ]),
+        };
+        assert!(matches!(msg.to_message(), Err(Error::DynamicMessage(_))));
+        assert_eq!(
+            serde_json::to_string(&msg).unwrap(),
+            r#"{"NewMessage":{"Text":"a\nb","Px":[-3,5]}}"#
+        );
+    }
+}
@@ -0,0 +1,247 @@
+//! # Entitlements
+//!
+//! Builds a report of requested feeds granted or denied by the server
+//! from `ApplicationMessageRequestAck` messages.
+//!
+//! The ack lists each requested feed (`RefApplID`) with `ApplResponseError` set
+//! if the request was rejected. A feed without error is granted.
+//!
+//! # Examples
+//!
+//! ```rust,ignore
+//! use quotesdirectlib::entitlements::EntitlementReport;
+//!
+//! let mut report = EntitlementReport::new();
+//! report.request("1");
+//! if let Message::ApplicationMessageRequestAck(ack) = &msg {
+//!     report.add_ack(ack);
+//! }
+//! for feed in report.denied() {
+//!     println!("feed {} denied: {}", feed.appl_id, feed.status);
+//! }
+//! ```
+//!
+use std::fmt::{Display, Formatter};
+
+use crate::fast::{ApplID, ApplicationMessageRequestAck, Connection, Message};
+
+/// `ApplResponseError` (1354) values.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum ApplResponseError {
+    ApplicationDoesNotExist,
+    MessagesNotAvailable,
+    NotAuthorized,
+    Other(u32),
+}
+
+impl ApplResponseError {
+    #[must_use]
+    pub fn from_fix(value: u32) -> Self {
+        match value {
+            0 => ApplResponseError::ApplicationDoesNotExist,
+            1 => ApplResponseError::MessagesNotAvailable,
+            2 => ApplResponseError::NotAuthorized,
+            _ => ApplResponseError::Other(value),
+        }
+    }
+}
+
+impl Display for ApplResponseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ApplResponseError::ApplicationDoesNotExist => f.write_str("feed does not exist"),
+            ApplResponseError::MessagesNotAvailable => f.write_str("messages not available"),
+            ApplResponseError::NotAuthorized => f.write_str("user not authorized"),
+            ApplResponseError::Other(code) => write!(f, "error code {code}"),
+        }
+    }
+}
+
+/// Outcome of a feed request.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum EntitlementStatus {
+    /// Requested, no ack received yet.
+    Pending,
+    Granted,
+    Denied(ApplResponseError),
+}
+
+impl Display for EntitlementStatus {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EntitlementStatus::Pending => f.write_str("no response"),
+            EntitlementStatus::Granted => f.write_str("granted"),
+            EntitlementStatus::Denied(error) => write!(f, "denied ({error})"),
+        }
+    }
+}
+
+/// Entitlement of one feed.
+#[derive(Debug, PartialEq, Clone)]
+pub struct FeedEntitlement {
+    pub appl_id: String,
+    pub status: EntitlementStatus,
+    pub news_source_id: Option<u32>,
+    pub connections: Vec<Connection>,
+}
+
+impl FeedEntitlement {
+    fn new(appl_id: &str) -> Self {
+        Self {
+            appl_id: appl_id.to_string(),
+            status: EntitlementStatus::Pending,
+            news_source_id: None,
+            connections: Vec::new(),
+        }
+    }
+
+    #[must_use]
+    pub fn is_granted(&self) -> bool {
+        self.status == EntitlementStatus::Granted
+    }
+
+    #[must_use]
+    pub fn is_denied(&self) -> bool {
+        matches!(self.status, EntitlementStatus::Denied(_))
+    }
+}
+
+/// Entitlements of requested feeds in request order.
+/// Feeds acked without being requested follow in ack order.
+#[derive(Debug, Default)]
+pub struct EntitlementReport {
+    feeds: Vec<FeedEntitlement>,
+}
+
+impl EntitlementReport {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record a feed request.
+    pub fn request(&mut self, appl_id: &str) {
+        self.entry(appl_id);
+    }
+
+    /// Update the report from an ack message.
+    pub fn process(&mut self, msg: &Message) {
+        if let Message::ApplicationMessageRequestAck(ack) = msg {
+            self.add_ack(ack);
+        }
+    }
+
+    /// Update the report from an ack.
+    pub fn add_ack(&mut self, ack: &ApplicationMessageRequestAck) {
+        for ApplID {
+            ref_appl_id,
+            appl_response_error,
+            news_source_id,
+            connections,
+            ..
+        } in &ack.appl_ids
+        {
+            let feed = self.entry(ref_appl_id);
+            feed.status = match appl_response_error {
+                Some(code) => EntitlementStatus::Denied(ApplResponseError::from_fix(*code)),
+                None => EntitlementStatus::Granted,
+            };
+            feed.news_source_id = *news_source_id;
+            feed.connections.clone_from(connections);
+        }
+    }
+
+    #[must_use]
+    pub fn get(&self, appl_id: &str) -> Option<&FeedEntitlement> {
+        self.feeds.iter().find(|feed| feed.appl_id == appl_id)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &FeedEntitlement> {
+        self.feeds.iter()
+    }
+
+    pub fn granted(&self) -> impl Iterator<Item = &FeedEntitlement> {
+        self.feeds.iter().filter(|feed| feed.is_granted())
+    }
+
+    pub fn denied(&self) -> impl Iterator<Item = &FeedEntitlement> {
+        self.feeds.iter().filter(|feed| feed.is_denied())
+    }
+
+    /// Requested feeds without ack.
+    pub fn pending(&self) -> impl Iterator<Item = &FeedEntitlement> {
+        self.feeds
+            .iter()
+            .filter(|feed| feed.status == EntitlementStatus::Pending)
+    }
+
+    /// All requested feeds were acked.
+    #[must_use]
+    pub fn is_complete(&self) -> bool {
+        self.pending().next().is_none()
+    }
+
+    fn entry(&mut self, appl_id: &str) -> &mut FeedEntitlement {
+        let index = self
+            .feeds
+            .iter()
+            .position(|feed| feed.appl_id == appl_id)
+            .unwrap_or_else(|| {
+                self.feeds.push(FeedEntitlement::new(appl_id));
+                self.feeds.len() - 1
+            });
+        &mut self.feeds[index]
+    }
+}
+
+impl Display for EntitlementReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for feed in &self.feeds {
+            writeln!(f, "feed {}: {}", feed.appl_id, feed.status)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::fast::fixtures::header;
+
+    fn appl_id(ref_appl_id: &str, appl_response_error: Option<u32>) -> ApplID {
+        ApplID {
+            ref_appl_id: ref_appl_id.to_string(),
+            appl_response_error,
+            raw_data: None,
+            news_source_id: None,
+            connections: vec![],
+        }
+    }
+
+    #[test]
+    fn test_report() {
+        let mut report = EntitlementReport::new();
+        for feed in ["1", "2", "3"] {
+            report.request(feed);
+        }
+        report.add_ack(&ApplicationMessageRequestAck {
+            message_type: "BX".to_string(),
+            msg_header: header(2),
+            appl_response_id: "1".to_string(),
+            appl_req_id: "1".to_string(),
+            appl_ids: vec![appl_id("1", None), appl_id("2", Some(2))],
+        });
+
+        assert!(report.get("1").unwrap().is_granted());
+        assert_eq!(
+            report.get("2").unwrap().status,
+            EntitlementStatus::Denied(ApplResponseError::NotAuthorized)
+        );
+        assert_eq!(report.pending().count(), 1);
+        assert!(!report.is_complete());
+        assert_eq!(
+            report.to_string(),
+            "feed 1: granted\nfeed 2: denied (user not authorized)\nfeed 3: no response\n"
+        );
+    }
+}
@@ -0,0 +1,100 @@
+//! # Normalized market events stream
+//!
+//! # Examples
+//!
+//! ```rust,ignore
+//! use futures::StreamExt;
+//! use quotesdirectlib::events::MarketEventStream;
+//!
+//! let mut events = MarketEventStream::new(messages);
+//! while let Some(event) = events.next().await {
+//!     println!("{event:?}");
+//! }
+//! ```
+//!
+use futures_core::Stream;
+use std::borrow::Borrow;
+use std::collections::VecDeque;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use crate::fast::Message;
+use crate::market::{MarketEvent, normalize_into};
+
+/// Stream adapter that turns a stream of messages into normalized market events.
+pub struct MarketEventStream<S> {
+    messages: S,
+    pending: VecDeque<MarketEvent>,
+    buffer: Vec<MarketEvent>,
+}
+
+impl<S> MarketEventStream<S> {
+    pub fn new(messages: S) -> Self {
+        Self {
+            messages,
+            pending: VecDeque::new(),
+            buffer: Vec::new(),
+        }
+    }
+}
+
+impl<S, M> Stream for MarketEventStream<S>
+where
+    S: Stream<Item = M> + Unpin,
+    M: Borrow<Message>,
+{
+    type Item = MarketEvent;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+        loop {
+            if let Some(event) = this.pending.pop_front() {
+                return Poll::Ready(Some(event));
+            }
+            match Pin::new(&mut this.messages).poll_next(cx) {
+                Poll::Ready(Some(msg)) => {
+                    normalize_into(msg.borrow(), &mut this.buffer);
+                    this.pending.extend(this.buffer.drain(..));
+                }
+                Poll::Ready(None) => return Poll::Ready(None),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::fast::fixtures::{inc_refresh, md_entry};
+    use std::task::Waker;
+
+    /// Stream over an iterator, always ready.
+    struct Ready<I>(I);
+
+    impl<I: Iterator + Unpin> Stream for Ready<I> {
+        type Item = I::Item;
+
+        fn poll_next(mut self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Option<I::Item>> {
+            Poll::Ready(self.0.next())
+        }
+    }
+
+    #[test]
+    fn test_event_stream() {
+        let messages = vec![
+            inc_refresh(1, vec![md_entry(100, 1, None), md_entry(101, 1, None)]),
+            inc_refresh(2, vec![md_entry(102, 1, None)]),
+        ];
+        let mut events = MarketEventStream::new(Ready(messages.into_iter()));
+        let mut cx = Context::from_waker(Waker::noop());
+        let mut next = || Pin::new(&mut events).poll_next(&mut cx);
+        for security_id in [100, 101, 102] {
+            let Poll::Ready(Some(MarketEvent::BookUpdate(update))) = next() else {
+                panic!("expected book update");
+            };
+            assert_eq!(update.security_id, security_id);
+        }
+        assert!(matches!(next(), Poll::Ready(None)));
+    }
+}
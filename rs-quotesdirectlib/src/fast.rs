@@ -35,9 +35,16 @@
 //! }
 //! ```
 //!
+use chrono::{DateTime, NaiveDate, Utc};
 use fastlib::Decimal;
 use serde::{Deserialize, Serialize};
 
+use crate::calendar::Session;
+use crate::cfi::Cfi;
+use crate::handler::UserStatus;
+use crate::lifecycle::{EventKind, LifecycleEvent};
+use crate::symbol::CqgSymbol;
+
 pub const TEMPLATES_XML: &str = include_str!("../templates.xml");
 
 //
@@ -60,6 +67,27 @@ pub enum Message {
     UserNotification(UserNotification),
 }
 
+impl Message {
+    /// Header of any message.
+    #[must_use]
+    pub fn msg_header(&self) -> &MsgHeader {
+        match self {
+            Message::MDIncRefresh(m) => &m.msg_header,
+            Message::MDSecurityDefinition(m) => &m.msg_header,
+            Message::MDSnapshotFullRefresh(m) => &m.msg_header,
+            Message::MDHeartbeat(m) => &m.msg_header,
+            Message::MDLogon(m) => &m.msg_header,
+            Message::MDLogout(m) => &m.msg_header,
+            Message::MDSecurityDefinitionRequest(m) => &m.msg_header,
+            Message::SequenceReset(m) => &m.msg_header,
+            Message::MDSecurityStatus(m) => &m.msg_header,
+            Message::News(m) => &m.msg_header,
+            Message::ApplicationMessageRequestAck(m) => &m.msg_header,
+            Message::UserNotification(m) => &m.msg_header,
+        }
+    }
+}
+
 //
 // <template dictionary="1" id="1" name="MDIncRefresh" />
 //
@@ -69,6 +97,7 @@ pub struct IncRefresh {
     pub message_type: String,
     #[serde(flatten)]
     pub msg_header: MsgHeader,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub trade_date: Option<u32>,
     #[serde(rename = "MDEntries")]
     pub md_entries: Vec<MDEntry>,
@@ -77,9 +106,9 @@ pub struct IncRefresh {
 #[derive(Serialize, Deserialize, PartialEq, Eq, Hash, Clone, Debug)]
 #[serde(rename_all = "PascalCase")]
 pub struct MDEntry {
-    #[serde(rename = "MDUpdateAction")]
+    #[serde(rename = "MDUpdateAction", skip_serializing_if = "Option::is_none")]
     pub md_update_action: Option<u32>,
-    #[serde(rename = "MDPriceLevel")]
+    #[serde(rename = "MDPriceLevel", skip_serializing_if = "Option::is_none")]
     pub md_price_level: Option<u32>,
     #[serde(rename = "MDEntryType")]
     pub md_entry_type: String,
@@ -88,20 +117,25 @@ pub struct MDEntry {
     #[serde(rename = "SecurityIDSource")]
     pub security_id_source: u32,
     pub rpt_seq: u32,
-    #[serde(rename = "MDEntryPx")]
+    #[serde(rename = "MDEntryPx", skip_serializing_if = "Option::is_none")]
     pub md_entry_px: Option<Decimal>,
     #[serde(rename = "MDEntryTime")]
     pub md_entry_time: u32,
-    #[serde(rename = "MDEntrySize")]
+    #[serde(rename = "MDEntrySize", skip_serializing_if = "Option::is_none")]
     pub md_entry_size: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub quote_condition: Option<String>,
-    #[serde(rename = "MDQuoteType")]
+    #[serde(rename = "MDQuoteType", skip_serializing_if = "Option::is_none")]
     pub md_quote_type: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub trade_condition: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub trade_volume: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub aggressor_side: Option<u32>,
-    #[serde(rename = "MDWorkupState")]
+    #[serde(rename = "MDWorkupState", skip_serializing_if = "Option::is_none")]
     pub md_workup_state: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub parties: Option<Vec<Party>>,
 }
 
@@ -116,15 +150,18 @@ pub struct Party {
 //
 // <template dictionary="2" id="2" name="MDSecurityDefinition" />
 //
-#[derive(Serialize, Deserialize, PartialEq, Debug)]
+#[derive(Serialize, Deserialize, PartialEq, Clone, Debug)]
 #[serde(rename_all = "PascalCase")]
 pub struct SecurityDefinition {
     pub message_type: String,
     #[serde(flatten)]
     pub msg_header: MsgHeader,
     pub tot_num_reports: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub events: Option<Vec<Event>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub security_group: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub symbol: Option<String>,
     pub security_name: String,
     pub security_desc: String,
@@ -134,30 +171,105 @@ pub struct SecurityDefinition {
     pub security_id_source: u32,
     #[serde(rename = "CFICode")]
     pub cfi_code: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub security_exchange: Option<String>,
-    #[serde(rename = "CQGSecurityName")]
+    #[serde(rename = "CQGSecurityName", skip_serializing_if = "Option::is_none")]
     pub cqg_security_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub strike_price: Option<Decimal>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub strike_currency: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub currency: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub settl_currency: Option<String>,
-    #[serde(rename = "MDFeedTypes")]
+    #[serde(rename = "MDFeedTypes", skip_serializing_if = "Option::is_none")]
     pub md_feed_types: Option<Vec<FeedType>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub instr_attrib: Option<Vec<InstrAttrib>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub maturity_month_year: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub min_price_increment: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub min_price_increment_amount: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub display_factor: Option<Decimal>,
     #[serde(rename = "ApplID")]
     pub appl_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub most_active_flag: Option<String>,
     pub connections: Vec<Connection>,
     pub trading_sessions: Vec<TradingSession>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub underlyings: Option<Vec<Underlying>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub security_sub_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub legs: Option<Vec<Leg>>,
 }
 
+impl SecurityDefinition {
+    /// Lifecycle events of the instrument.
+    pub fn lifecycle_events(&self) -> impl Iterator<Item = LifecycleEvent> {
+        self.events.iter().flatten().map(LifecycleEvent::from_fix)
+    }
+
+    /// First lifecycle event of the kind.
+    #[must_use]
+    pub fn lifecycle_event(&self, kind: EventKind) -> Option<LifecycleEvent> {
+        self.lifecycle_events().find(|e| e.kind == kind)
+    }
+
+    /// Time the instrument becomes available for trading.
+    #[must_use]
+    pub fn activation_time(&self) -> Option<DateTime<Utc>> {
+        self.lifecycle_event(EventKind::Activation)?.time
+    }
+
+    /// Last eligible trade date.
+    #[must_use]
+    pub fn last_trade_date(&self) -> Option<NaiveDate> {
+        self.lifecycle_event(EventKind::LastEligibleTradeDate)?.date
+    }
+
+    /// Time of the last eligible trade.
+    /// Falls back to the close of the trading session on the last trade date
+    /// if the event carries no time.
+    #[must_use]
+    pub fn last_trade_time(&self) -> Option<DateTime<Utc>> {
+        let event = self.lifecycle_event(EventKind::LastEligibleTradeDate)?;
+        event.time.or_else(|| {
+            self.trading_sessions
+                .iter()
+                .filter_map(Session::from_fix)
+                .filter(|s| Some(s.trade_date) == event.date)
+                .map(|s| s.close)
+                .max()
+        })
+    }
+
+    /// Time the instrument stops trading: inactivation if published, otherwise the last trade time.
+    #[must_use]
+    pub fn expiration_time(&self) -> Option<DateTime<Utc>> {
+        self.lifecycle_event(EventKind::Inactivation)
+            .and_then(|e| e.time)
+            .or_else(|| self.last_trade_time())
+    }
+
+    /// Classification of the instrument.
+    #[must_use]
+    pub fn cfi(&self) -> Cfi {
+        Cfi::parse(&self.cfi_code)
+    }
+
+    /// Parsed `CQGSecurityName`.
+    #[must_use]
+    pub fn cqg_symbol(&self) -> Option<CqgSymbol> {
+        self.cqg_security_name.as_deref()?.parse().ok()
+    }
+}
+
 #[derive(Serialize, Deserialize, PartialEq, Eq, Hash, Clone, Debug)]
 #[serde(rename_all = "PascalCase")]
 pub struct Event {
@@ -178,6 +290,7 @@ pub struct FeedType {
 #[serde(rename_all = "PascalCase")]
 pub struct InstrAttrib {
     pub instr_attrib_type: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub instr_attrib_value: Option<String>,
 }
 
@@ -228,6 +341,14 @@ pub struct Leg {
     pub leg_strike_price: Decimal,
 }
 
+impl Leg {
+    /// Classification of the leg instrument.
+    #[must_use]
+    pub fn cfi(&self) -> Cfi {
+        Cfi::parse(&self.leg_cfi_code)
+    }
+}
+
 //
 // <template dictionary="3" id="3" name="MDSnapshotFullRefresh">
 //
@@ -244,7 +365,10 @@ pub struct SnapshotFullRefresh {
     pub security_id: u32,
     #[serde(rename = "SecurityIDSource")]
     pub security_id_source: u32,
-    #[serde(rename = "MDSecurityTradingStatus")]
+    #[serde(
+        rename = "MDSecurityTradingStatus",
+        skip_serializing_if = "Option::is_none"
+    )]
     pub md_security_trading_status: Option<u32>,
     #[serde(rename = "MDEntries")]
     pub md_entries: Vec<MDEntrySnapshot>,
@@ -254,14 +378,15 @@ pub struct SnapshotFullRefresh {
 pub struct MDEntrySnapshot {
     #[serde(rename = "MDEntryType")]
     pub md_entry_type: String,
-    #[serde(rename = "MDEntryPx")]
+    #[serde(rename = "MDEntryPx", skip_serializing_if = "Option::is_none")]
     pub md_entry_px: Option<Decimal>,
-    #[serde(rename = "MDEntrySize")]
+    #[serde(rename = "MDEntrySize", skip_serializing_if = "Option::is_none")]
     pub md_entry_size: Option<i32>,
+    #[serde(rename = "QuoteCondition", skip_serializing_if = "Option::is_none")]
     pub quote_condition: Option<String>,
-    #[serde(rename = "MDPriceLevel")]
+    #[serde(rename = "MDPriceLevel", skip_serializing_if = "Option::is_none")]
     pub md_price_level: Option<u32>,
-    #[serde(rename = "MDWorkupState")]
+    #[serde(rename = "MDWorkupState", skip_serializing_if = "Option::is_none")]
     pub md_workup_state: Option<u32>,
 }
 
@@ -298,6 +423,7 @@ pub struct Logout {
     pub message_type: String,
     #[serde(flatten)]
     pub msg_header: MsgHeader,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub text: Option<String>,
 }
 
@@ -312,6 +438,7 @@ pub struct SecurityDefinitionRequest {
     pub msg_header: MsgHeader,
     #[serde(rename = "ApplID")]
     pub appl_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub text: Option<String>,
 }
 
@@ -336,18 +463,20 @@ pub struct SecurityStatus {
     pub message_type: String,
     #[serde(flatten)]
     pub msg_header: MsgHeader,
-    #[serde(rename = "SecurityID")]
+    #[serde(rename = "SecurityID", skip_serializing_if = "Option::is_none")]
     pub security_id: Option<u32>,
-    #[serde(rename = "SecurityIDSource")]
+    #[serde(rename = "SecurityIDSource", skip_serializing_if = "Option::is_none")]
     pub security_id_source: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub symbol: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub security_trading_status: Option<u32>,
 }
 
 //
 // <template dictionary="10" id="10" name="News" />
 //
-#[derive(Serialize, Deserialize, PartialEq, Debug)]
+#[derive(Serialize, Deserialize, PartialEq, Clone, Debug)]
 #[serde(rename_all = "PascalCase")]
 pub struct News {
     pub message_type: String,
@@ -360,15 +489,23 @@ pub struct News {
     pub news_id: String,
     #[serde(rename = "NewsSourceID")]
     pub news_source_id: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub last_fragment: Option<String>,
-    #[serde(rename = "NewsRefIDs")]
+    #[serde(rename = "NewsRefIDs", skip_serializing_if = "Option::is_none")]
     pub news_ref_ids: Option<Vec<NewsRefID>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub orig_time: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub urgency: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub news_branding: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub accession_number: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub encoded_headline: Option<Vec<u8>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub encoded_text: Option<Vec<u8>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub news_categories: Option<Vec<NewsCategory>>,
 }
 
@@ -409,9 +546,11 @@ pub struct ApplicationMessageRequestAck {
 pub struct ApplID {
     #[serde(rename = "RefApplID")]
     pub ref_appl_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub appl_response_error: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub raw_data: Option<Vec<u8>>,
-    #[serde(rename = "NewsSourceID")]
+    #[serde(rename = "NewsSourceID", skip_serializing_if = "Option::is_none")]
     pub news_source_id: Option<u32>,
     pub connections: Vec<Connection>,
 }
@@ -429,6 +568,13 @@ pub struct UserNotification {
     pub text: String,
 }
 
+impl UserNotification {
+    #[must_use]
+    pub fn status(&self) -> UserStatus {
+        UserStatus::from_fix(self.user_status)
+    }
+}
+
 //
 // <template name="MsgHeader" />
 //
@@ -443,3 +589,130 @@ pub struct MsgHeader {
     #[serde(rename = "SendingTime")]
     pub sending_time: u64,
 }
+
+#[cfg(test)]
+mod test {
+    use super::fixtures::header;
+    use super::*;
+    use fastlib::{Decoder, Encoder};
+
+    #[test]
+    fn test_snapshot_quote_condition() {
+        let msg = Message::MDSnapshotFullRefresh(SnapshotFullRefresh {
+            message_type: "W".to_string(),
+            msg_header: header(1),
+            last_msg_seq_num_processed: 1,
+            tot_num_reports: 1,
+            rpt_seq: 1,
+            security_id: 100,
+            security_id_source: 100,
+            md_security_trading_status: None,
+            md_entries: vec![MDEntrySnapshot {
+                md_entry_type: "0".to_string(),
+                md_entry_px: Some(Decimal::new(-2, 450000)),
+                md_entry_size: Some(1),
+                quote_condition: Some("K".to_string()),
+                md_price_level: Some(1),
+                md_workup_state: None,
+            }],
+        });
+        let mut encoder = Encoder::new_from_xml(TEMPLATES_XML).unwrap();
+        let raw = fastlib::to_vec(&mut encoder, &msg).unwrap();
+        let mut decoder = Decoder::new_from_xml(TEMPLATES_XML).unwrap();
+        let decoded: Message = fastlib::from_slice(&mut decoder, &raw).unwrap();
+        assert_eq!(decoded, msg);
+    }
+}
+
+/// Messages shared by the tests of several modules.
+#[cfg(test)]
+pub(crate) mod fixtures {
+    use super::*;
+
+    pub(crate) fn header(msg_seq_num: u32) -> MsgHeader {
+        MsgHeader {
+            appl_ver_id: "8".to_string(),
+            sender_comp_id: "CQG".to_string(),
+            msg_seq_num,
+            sending_time: 20250620102247123,
+        }
+    }
+
+    pub(crate) fn heartbeat(msg_seq_num: u32) -> Message {
+        Message::MDHeartbeat(Heartbeat {
+            message_type: "0".to_string(),
+            msg_header: header(msg_seq_num),
+        })
+    }
+
+    /// New bid of one lot at the top level.
+    pub(crate) fn md_entry(
+        security_id: u32,
+        rpt_seq: u32,
+        md_entry_px: Option<Decimal>,
+    ) -> MDEntry {
+        MDEntry {
+            md_update_action: Some(0),
+            md_price_level: Some(1),
+            md_entry_type: "0".to_string(),
+            security_id,
+            security_id_source: 100,
+            rpt_seq,
+            md_entry_px,
+            md_entry_time: 102247123,
+            md_entry_size: Some(1),
+            quote_condition: None,
+            md_quote_type: None,
+            trade_condition: None,
+            trade_volume: None,
+            aggressor_side: None,
+            md_workup_state: None,
+            parties: None,
+        }
+    }
+
+    /// Future without optional fields.
+    pub(crate) fn definition(security_id: u32) -> SecurityDefinition {
+        SecurityDefinition {
+            message_type: "d".to_string(),
+            msg_header: header(1),
+            tot_num_reports: 1,
+            events: None,
+            security_group: None,
+            symbol: None,
+            security_name: String::new(),
+            security_desc: String::new(),
+            security_id,
+            security_id_source: 8,
+            cfi_code: "FFIXSX".to_string(),
+            security_exchange: None,
+            cqg_security_name: None,
+            strike_price: None,
+            strike_currency: None,
+            currency: None,
+            settl_currency: None,
+            md_feed_types: None,
+            instr_attrib: None,
+            maturity_month_year: None,
+            min_price_increment: None,
+            min_price_increment_amount: None,
+            display_factor: None,
+            appl_id: "1".to_string(),
+            most_active_flag: None,
+            connections: vec![],
+            trading_sessions: vec![],
+            underlyings: None,
+            security_sub_type: None,
+            legs: None,
+        }
+    }
+
+    pub(crate) fn inc_refresh(msg_seq_num: u32, md_entries: Vec<MDEntry>) -> Message {
+        Message::MDIncRefresh(IncRefresh {
+            message_type: "X".to_string(),
+            msg_header: header(msg_seq_num),
+            trade_date: None,
+            md_entries,
+        })
+    }
+}
@@ -0,0 +1,163 @@
+//! # Message handler
+//!
+//! Typed callbacks for every Quotes Direct [`Message`]. All callbacks default to no-op,
+//! so a handler implements only the messages it is interested in
+//! and unexpected messages are ignored rather than treated as errors.
+//!
+//! # Examples
+//!
+//! ```rust,ignore
+//! use quotesdirectlib::fast::UserNotification;
+//! use quotesdirectlib::handler::MessageHandler;
+//!
+//! struct Notifications;
+//!
+//! impl MessageHandler for Notifications {
+//!     fn on_user_notification(&mut self, msg: &UserNotification) {
+//!         println!("{:?}: {}", msg.status(), msg.text);
+//!     }
+//! }
+//!
+//! Notifications.on_message(&msg);
+//! ```
+//!
+use crate::fast::{
+    ApplicationMessageRequestAck, Heartbeat, IncRefresh, Logon, Logout, Message, News,
+    SecurityDefinition, SecurityDefinitionRequest, SecurityStatus, SequenceReset,
+    SnapshotFullRefresh, UserNotification,
+};
+
+/// Callbacks for each message type.
+pub trait MessageHandler {
+    /// Dispatch the message to the callback of its type.
+    fn on_message(&mut self, msg: &Message) {
+        dispatch(self, msg);
+    }
+
+    fn on_inc_refresh(&mut self, _msg: &IncRefresh) {}
+
+    fn on_security_definition(&mut self, _msg: &SecurityDefinition) {}
+
+    fn on_snapshot(&mut self, _msg: &SnapshotFullRefresh) {}
+
+    fn on_heartbeat(&mut self, _msg: &Heartbeat) {}
+
+    fn on_logon(&mut self, _msg: &Logon) {}
+
+    fn on_logout(&mut self, _msg: &Logout) {}
+
+    fn on_security_definition_request(&mut self, _msg: &SecurityDefinitionRequest) {}
+
+    fn on_sequence_reset(&mut self, _msg: &SequenceReset) {}
+
+    fn on_security_status(&mut self, _msg: &SecurityStatus) {}
+
+    fn on_news(&mut self, _msg: &News) {}
+
+    fn on_request_ack(&mut self, _msg: &ApplicationMessageRequestAck) {}
+
+    fn on_user_notification(&mut self, _msg: &UserNotification) {}
+}
+
+/// Call the handler's callback for the message type.
+pub fn dispatch<H: MessageHandler + ?Sized>(handler: &mut H, msg: &Message) {
+    match msg {
+        Message::MDIncRefresh(m) => handler.on_inc_refresh(m),
+        Message::MDSecurityDefinition(m) => handler.on_security_definition(m),
+        Message::MDSnapshotFullRefresh(m) => handler.on_snapshot(m),
+        Message::MDHeartbeat(m) => handler.on_heartbeat(m),
+        Message::MDLogon(m) => handler.on_logon(m),
+        Message::MDLogout(m) => handler.on_logout(m),
+        Message::MDSecurityDefinitionRequest(m) => handler.on_security_definition_request(m),
+        Message::SequenceReset(m) => handler.on_sequence_reset(m),
+        Message::MDSecurityStatus(m) => handler.on_security_status(m),
+        Message::News(m) => handler.on_news(m),
+        Message::ApplicationMessageRequestAck(m) => handler.on_request_ack(m),
+        Message::UserNotification(m) => handler.on_user_notification(m),
+    }
+}
+
+/// `UserStatus` (926) values.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum UserStatus {
+    LoggedIn,
+    NotLoggedIn,
+    UserNotRecognised,
+    PasswordIncorrect,
+    PasswordChanged,
+    /// See `Text` for details.
+    Other,
+    ForcedLogout,
+    SessionShutdownWarning,
+    Unknown(u32),
+}
+
+impl UserStatus {
+    #[must_use]
+    pub fn from_fix(value: u32) -> Self {
+        match value {
+            1 => UserStatus::LoggedIn,
+            2 => UserStatus::NotLoggedIn,
+            3 => UserStatus::UserNotRecognised,
+            4 => UserStatus::PasswordIncorrect,
+            5 => UserStatus::PasswordChanged,
+            6 => UserStatus::Other,
+            7 => UserStatus::ForcedLogout,
+            8 => UserStatus::SessionShutdownWarning,
+            _ => UserStatus::Unknown(value),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::fast::fixtures::{header, heartbeat};
+
+    #[derive(Default)]
+    struct Counter {
+        heartbeats: u32,
+        notifications: Vec<(UserStatus, String)>,
+    }
+
+    impl MessageHandler for Counter {
+        fn on_heartbeat(&mut self, _msg: &Heartbeat) {
+            self.heartbeats += 1;
+        }
+
+        fn on_user_notification(&mut self, msg: &UserNotification) {
+            self.notifications.push((msg.status(), msg.text.clone()));
+        }
+    }
+
+    #[test]
+    fn test_dispatch() {
+        let messages = [
+            heartbeat(1),
+            Message::SequenceReset(SequenceReset {
+                message_type: "4".to_string(),
+                msg_header: header(2),
+                new_seq_no: 10,
+            }),
+            Message::UserNotification(UserNotification {
+                message_type: "CB".to_string(),
+                msg_header: header(10),
+                user_status: 8,
+                text: "Server restart in 5 minutes".to_string(),
+            }),
+        ];
+        let mut counter = Counter::default();
+        for msg in &messages {
+            counter.on_message(msg);
+        }
+        assert_eq!(counter.heartbeats, 1);
+        assert_eq!(
+            counter.notifications,
+            vec![(
+                UserStatus::SessionShutdownWarning,
+                "Server restart in 5 minutes".to_string()
+            )]
+        );
+        assert_eq!(messages[2].msg_header().msg_seq_num, 10);
+    }
+}
@@ -7,11 +7,55 @@
 //! - reading TCP and UDP packets
 //! - parsing incoming FAST messages
 //! - generating outgoing FIX messages
+//! - normalizing market data messages into market events
+//! - extracting trade tape with aggressor and workup details
+//! - tracking session statistics (open, high, low, settlement, open interest)
+//! - building OHLCV bars from the trade tape
+//! - tracking trading status and price level order books
+//! - querying trading session calendars
+//! - keeping a security master with instrument lifecycle events
+//! - building options chains
+//! - resolving spread legs and computing implied prices
+//! - classifying instruments by ISO 10962 CFI codes
+//! - parsing and formatting CQG symbols
+//! - resolving front-month and continuous contracts
+//! - reassembling and searching news stories
+//! - reporting feed entitlements
+//! - dispatching messages to typed handlers
+//! - decoding messages with per-transport dictionary reset policy
+//! - recovering from FAST decode errors
+//! - loading templates at runtime and checking their compatibility
+//! - decoding messages of any template into dynamic fields
 //!
+pub mod bars;
+pub mod book;
+pub mod calendar;
+pub mod cfi;
+pub mod continuous;
+pub mod decoder;
+pub mod dynamic;
+pub mod entitlements;
 pub mod fast;
 pub mod fix;
+pub mod handler;
+pub mod lifecycle;
+pub mod market;
+pub mod news;
+pub mod options;
+pub mod recovery;
+pub mod secmaster;
+pub mod sequence;
+pub mod spreads;
+pub mod stats;
+pub mod status;
+pub mod symbol;
 pub mod sync;
+pub mod templates;
+pub mod time;
+pub mod trades;
 
+#[cfg(feature = "tokio")]
+pub mod events;
 #[cfg(feature = "tokio")]
 pub mod packets;
 
@@ -23,6 +67,29 @@ pub enum Error {
     #[error("Invalid packet length: {0}")]
     InvalidPacketLength(u64),
 
+    /// Errors happened due to malformed CQG symbol.
+    #[error("Invalid CQG symbol: {0}")]
+    InvalidSymbol(String),
+
+    /// Errors happened due to text in unsupported `MessageEncoding`.
+    #[error("Unsupported message encoding: {0}")]
+    UnsupportedEncoding(String),
+
+    /// Errors happened due to malformed templates XML.
+    #[error("Invalid templates: {0}")]
+    InvalidTemplates(String),
+
+    /// Errors happened due to templates incompatible with the typed messages.
+    #[error("Templates do not match message types: {}", .0.iter().map(ToString::to_string).collect::<Vec<_>>().join("; "))]
+    TemplateMismatch(Vec<templates::Mismatch>),
+
+    /// Errors happened due to dynamic message not matching the typed messages.
+    #[error("Cannot convert dynamic message: {0}")]
+    DynamicMessage(String),
+
+    #[error(transparent)]
+    FastError(#[from] fastlib::Error),
+
     #[error(transparent)]
     IoError(#[from] std::io::Error),
 }
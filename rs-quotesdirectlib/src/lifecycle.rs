@@ -0,0 +1,150 @@
+//! # Instrument lifecycle
+//!
+//! Typed `SecurityDefinition.Events` (activation, last eligible trade date, delivery dates, etc.)
+//! and accessors for the dates roll scheduling depends on.
+//!
+//! # Examples
+//!
+//! ```rust,ignore
+//! use quotesdirectlib::lifecycle::EventKind;
+//!
+//! println!("last trade: {:?}", definition.last_trade_time());
+//! if let Some(event) = definition.lifecycle_event(EventKind::FirstNoticeDate) {
+//!     println!("first notice: {:?}", event.date);
+//! }
+//! ```
+//!
+use chrono::{DateTime, NaiveDate, Utc};
+
+use crate::fast::Event;
+use crate::time::{local_mkt_date, utc_timestamp};
+
+/// `EventType` (865) values.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum EventKind {
+    Activation,
+    Inactivation,
+    LastEligibleTradeDate,
+    FirstDeliveryDate,
+    LastDeliveryDate,
+    FirstIntentDate,
+    LastIntentDate,
+    PositionRemovalDate,
+    FirstNoticeDate,
+    LastNoticeDate,
+    FirstExerciseDate,
+    Other(u32),
+}
+
+impl EventKind {
+    #[must_use]
+    pub fn from_fix(value: u32) -> Self {
+        match value {
+            5 => EventKind::Activation,
+            6 => EventKind::Inactivation,
+            7 => EventKind::LastEligibleTradeDate,
+            13 => EventKind::FirstDeliveryDate,
+            14 => EventKind::LastDeliveryDate,
+            17 => EventKind::FirstIntentDate,
+            18 => EventKind::LastIntentDate,
+            19 => EventKind::PositionRemovalDate,
+            103 => EventKind::FirstNoticeDate,
+            104 => EventKind::LastNoticeDate,
+            105 => EventKind::FirstExerciseDate,
+            _ => EventKind::Other(value),
+        }
+    }
+}
+
+/// Instrument lifecycle event.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub struct LifecycleEvent {
+    pub kind: EventKind,
+    /// `EventDate` (866).
+    pub date: Option<NaiveDate>,
+    /// `EventTime` (1145).
+    pub time: Option<DateTime<Utc>>,
+}
+
+impl LifecycleEvent {
+    #[must_use]
+    pub fn from_fix(event: &Event) -> Self {
+        Self {
+            kind: EventKind::from_fix(event.event_type),
+            date: local_mkt_date(event.event_date),
+            time: utc_timestamp(event.event_time),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::fast::fixtures;
+    use crate::fast::{SecurityDefinition, TradingSession};
+    use crate::secmaster::SecurityMaster;
+
+    fn definition(events: Vec<Event>) -> SecurityDefinition {
+        SecurityDefinition {
+            events: Some(events),
+            maturity_month_year: Some(202509),
+            trading_sessions: vec![TradingSession {
+                trade_date: 20250919,
+                trad_ses_start_time: 20250918210000000,
+                trad_ses_open_time: 20250918220000000,
+                trad_ses_close_time: 20250919133000000,
+                trad_ses_end_time: 20250919133000000,
+            }],
+            ..fixtures::definition(1)
+        }
+    }
+
+    #[test]
+    fn test_lifecycle() {
+        let sd = definition(vec![
+            Event {
+                event_type: 5,
+                event_date: 20240620,
+                event_time: 20240620220000000,
+            },
+            Event {
+                event_type: 7,
+                event_date: 20250919,
+                event_time: 0,
+            },
+        ]);
+        assert_eq!(sd.lifecycle_events().count(), 2);
+        assert_eq!(sd.activation_time(), utc_timestamp(20240620220000000));
+        assert_eq!(sd.last_trade_date(), NaiveDate::from_ymd_opt(2025, 9, 19));
+        assert_eq!(sd.last_trade_time(), utc_timestamp(20250919133000000));
+        assert_eq!(sd.expiration_time(), sd.last_trade_time());
+        assert!(sd.lifecycle_event(EventKind::FirstNoticeDate).is_none());
+    }
+
+    #[test]
+    fn test_expiring_between() {
+        let mut master = SecurityMaster::new();
+        for (security_id, time) in [(1, 20250919133000000), (2, 20251219143000000), (3, 0)] {
+            let mut sd = definition(vec![Event {
+                event_type: 7,
+                event_date: 20250919,
+                event_time: time,
+            }]);
+            sd.security_id = security_id;
+            master.insert(sd);
+        }
+        let from = utc_timestamp(20250901000000000).unwrap();
+        let to = utc_timestamp(20251231000000000).unwrap();
+        let expiring: Vec<_> = master
+            .expiring_between(from, to)
+            .iter()
+            .map(|sd| sd.security_id)
+            .collect();
+        assert_eq!(expiring, vec![1, 3, 2]);
+        assert!(
+            master
+                .expiring_between(from, utc_timestamp(20250919133000000).unwrap())
+                .is_empty()
+        );
+    }
+}
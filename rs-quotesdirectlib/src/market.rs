@@ -0,0 +1,610 @@
+//! # Normalized market events
+//!
+//! Translates FIX semantics of `MDIncRefresh`, `MDSnapshotFullRefresh` and `MDSecurityStatus` messages
+//! into [`MarketEvent`]s, so consumers do not need to know what `MDEntryType` or `MDUpdateAction` values mean.
+//!
+//! [Application Messages](https://help.cqg.com/apihelp/#!Documents/applicationmessagesquotesdirect.htm)
+//!
+//! # Examples
+//!
+//! ```rust,ignore
+//! use quotesdirectlib::market::{MarketEvent, normalize};
+//!
+//! for event in normalize(&msg) {
+//!     match event {
+//!         MarketEvent::BookUpdate(_) => {}
+//!         MarketEvent::Trade(_) => {}
+//!         MarketEvent::Statistic(_) => {}
+//!         MarketEvent::StatusChange(_) => {}
+//!         MarketEvent::SessionEvent(_) => {}
+//!     }
+//! }
+//! ```
+//!
+use chrono::{DateTime, NaiveDate, Utc};
+use fastlib::Decimal;
+use std::cmp::Ordering;
+
+use crate::fast::{
+    IncRefresh, MDEntry, MDEntrySnapshot, Message, SecurityStatus, SnapshotFullRefresh,
+};
+use crate::time::{local_mkt_date, utc_time_on, utc_timestamp};
+
+/// `MDEntryType` (269) values.
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+pub enum EntryType {
+    Bid,
+    Offer,
+    Trade,
+    OpeningPrice,
+    SettlementPrice,
+    SessionHigh,
+    SessionLow,
+    ClearedVolume,
+    OpenInterest,
+    EmptyBook,
+    ImpliedBid,
+    ImpliedOffer,
+    Other(String),
+}
+
+impl EntryType {
+    #[must_use]
+    pub fn from_fix(value: &str) -> Self {
+        match value {
+            "0" => EntryType::Bid,
+            "1" => EntryType::Offer,
+            "2" => EntryType::Trade,
+            "4" => EntryType::OpeningPrice,
+            "6" => EntryType::SettlementPrice,
+            "7" => EntryType::SessionHigh,
+            "8" => EntryType::SessionLow,
+            "B" => EntryType::ClearedVolume,
+            "C" => EntryType::OpenInterest,
+            "J" => EntryType::EmptyBook,
+            "E" => EntryType::ImpliedBid,
+            "F" => EntryType::ImpliedOffer,
+            _ => EntryType::Other(value.to_string()),
+        }
+    }
+}
+
+/// `MDUpdateAction` (279) values.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum UpdateAction {
+    New,
+    Change,
+    Delete,
+    DeleteThru,
+    DeleteFrom,
+    Overlay,
+    Other(u32),
+}
+
+impl UpdateAction {
+    #[must_use]
+    pub fn from_fix(value: u32) -> Self {
+        match value {
+            0 => UpdateAction::New,
+            1 => UpdateAction::Change,
+            2 => UpdateAction::Delete,
+            3 => UpdateAction::DeleteThru,
+            4 => UpdateAction::DeleteFrom,
+            5 => UpdateAction::Overlay,
+            _ => UpdateAction::Other(value),
+        }
+    }
+}
+
+/// Side of the order book.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum Side {
+    Bid,
+    Offer,
+}
+
+/// `AggressorSide` (5797) values.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum Aggressor {
+    None,
+    Buy,
+    Sell,
+    Other(u32),
+}
+
+impl Aggressor {
+    #[must_use]
+    pub fn from_fix(value: u32) -> Self {
+        match value {
+            0 => Aggressor::None,
+            1 => Aggressor::Buy,
+            2 => Aggressor::Sell,
+            _ => Aggressor::Other(value),
+        }
+    }
+}
+
+/// `SecurityTradingStatus` (326) and `MDSecurityTradingStatus` (1682) values.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum TradingStatus {
+    Halt,
+    Close,
+    NewPriceIndication,
+    ReadyToTrade,
+    NotAvailableForTrading,
+    Unknown,
+    PreOpen,
+    PreCross,
+    Cross,
+    PostClose,
+    NoChange,
+    Other(u32),
+}
+
+impl TradingStatus {
+    #[must_use]
+    pub fn from_fix(value: u32) -> Self {
+        match value {
+            2 => TradingStatus::Halt,
+            4 => TradingStatus::Close,
+            15 => TradingStatus::NewPriceIndication,
+            17 => TradingStatus::ReadyToTrade,
+            18 => TradingStatus::NotAvailableForTrading,
+            20 => TradingStatus::Unknown,
+            21 => TradingStatus::PreOpen,
+            24 => TradingStatus::PreCross,
+            25 => TradingStatus::Cross,
+            26 => TradingStatus::PostClose,
+            103 => TradingStatus::NoChange,
+            _ => TradingStatus::Other(value),
+        }
+    }
+
+    /// The instrument accepts orders for continuous trading.
+    #[must_use]
+    pub fn is_tradable(self) -> bool {
+        self == TradingStatus::ReadyToTrade
+    }
+}
+
+/// Market statistic kinds.
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+pub enum StatisticKind {
+    OpeningPrice,
+    SettlementPrice,
+    SessionHigh,
+    SessionLow,
+    ClearedVolume,
+    OpenInterest,
+    Other(String),
+}
+
+/// Normalized market event.
+#[derive(Debug, PartialEq, Clone)]
+pub enum MarketEvent {
+    BookUpdate(BookUpdate),
+    Trade(TradeEvent),
+    Statistic(Statistic),
+    StatusChange(StatusChange),
+    SessionEvent(SessionEvent),
+}
+
+impl MarketEvent {
+    /// Instrument the event applies to, `None` for group-level status changes.
+    #[must_use]
+    pub fn security_id(&self) -> Option<u32> {
+        match self {
+            MarketEvent::BookUpdate(e) => Some(e.security_id),
+            MarketEvent::Trade(e) => Some(e.security_id),
+            MarketEvent::Statistic(e) => Some(e.security_id),
+            MarketEvent::StatusChange(e) => e.security_id,
+            MarketEvent::SessionEvent(
+                SessionEvent::EmptyBook { security_id, .. }
+                | SessionEvent::Snapshot { security_id, .. },
+            ) => Some(*security_id),
+        }
+    }
+}
+
+/// Price level update of an instrument's order book.
+#[derive(Debug, PartialEq, Clone)]
+pub struct BookUpdate {
+    pub security_id: u32,
+    pub rpt_seq: Option<u32>,
+    pub action: UpdateAction,
+    pub side: Side,
+    /// The update is for the implied book.
+    pub implied: bool,
+    pub level: Option<u32>,
+    pub price: Option<Decimal>,
+    pub size: Option<i32>,
+    pub time: Option<DateTime<Utc>>,
+    pub sending_time: Option<DateTime<Utc>>,
+}
+
+/// Trade reported by the exchange.
+#[derive(Debug, PartialEq, Clone)]
+pub struct TradeEvent {
+    pub security_id: u32,
+    pub rpt_seq: u32,
+    pub trade_date: Option<NaiveDate>,
+    pub price: Option<Decimal>,
+    pub size: Option<i32>,
+    pub aggressor: Option<Aggressor>,
+    /// Cumulative volume of the instrument for the session.
+    pub trade_volume: Option<u32>,
+    pub workup_state: Option<u32>,
+    pub trade_condition: Option<String>,
+    pub party_ids: Vec<u32>,
+    pub time: Option<DateTime<Utc>>,
+    pub sending_time: Option<DateTime<Utc>>,
+}
+
+/// Market statistic (opening price, settlement, session high/low, volume, open interest).
+#[derive(Debug, PartialEq, Clone)]
+pub struct Statistic {
+    pub security_id: u32,
+    /// `None` for snapshot entries.
+    pub rpt_seq: Option<u32>,
+    pub kind: StatisticKind,
+    pub trade_date: Option<NaiveDate>,
+    pub price: Option<Decimal>,
+    pub size: Option<i32>,
+    /// `MDQuoteType`, snapshot entries do not carry it.
+    pub quote_type: Option<u32>,
+    pub time: Option<DateTime<Utc>>,
+    pub sending_time: Option<DateTime<Utc>>,
+}
+
+/// Trading status of an instrument or a group of instruments (if only `symbol` is set).
+#[derive(Debug, PartialEq, Clone)]
+pub struct StatusChange {
+    pub security_id: Option<u32>,
+    pub symbol: Option<String>,
+    pub status: TradingStatus,
+    pub sending_time: Option<DateTime<Utc>>,
+}
+
+/// Events that affect the whole instrument's state.
+#[derive(Debug, PartialEq, Clone)]
+pub enum SessionEvent {
+    /// All price levels of the instrument are removed.
+    EmptyBook {
+        security_id: u32,
+        sending_time: Option<DateTime<Utc>>,
+    },
+    /// The snapshot that follows replaces the instrument's book and statistics.
+    Snapshot {
+        security_id: u32,
+        rpt_seq: u32,
+        last_msg_seq_num_processed: u32,
+        sending_time: Option<DateTime<Utc>>,
+    },
+}
+
+/// Translate a message into normalized market events.
+/// Messages that carry no market data produce no events.
+#[must_use]
+pub fn normalize(msg: &Message) -> Vec<MarketEvent> {
+    let mut events = Vec::new();
+    normalize_into(msg, &mut events);
+    events
+}
+
+/// Translate a message into normalized market events and append them to `events`.
+pub fn normalize_into(msg: &Message, events: &mut Vec<MarketEvent>) {
+    match msg {
+        Message::MDIncRefresh(m) => normalize_inc_refresh(m, events),
+        Message::MDSnapshotFullRefresh(m) => normalize_snapshot(m, events),
+        Message::MDSecurityStatus(m) => normalize_status(m, events),
+        _ => {}
+    }
+}
+
+fn normalize_inc_refresh(msg: &IncRefresh, events: &mut Vec<MarketEvent>) {
+    let sending_time = utc_timestamp(msg.msg_header.sending_time);
+    let trade_date = msg.trade_date.and_then(|d| local_mkt_date(u64::from(d)));
+    for entry in &msg.md_entries {
+        events.push(normalize_entry(entry, trade_date, sending_time));
+    }
+}
+
+fn normalize_entry(
+    entry: &MDEntry,
+    trade_date: Option<NaiveDate>,
+    sending_time: Option<DateTime<Utc>>,
+) -> MarketEvent {
+    let time = sending_time.and_then(|ts| utc_time_on(ts, entry.md_entry_time));
+    let entry_type = EntryType::from_fix(&entry.md_entry_type);
+    if let Some((side, implied)) = book_side(&entry_type) {
+        return MarketEvent::BookUpdate(BookUpdate {
+            security_id: entry.security_id,
+            rpt_seq: Some(entry.rpt_seq),
+            action: UpdateAction::from_fix(entry.md_update_action.unwrap_or(0)),
+            side,
+            implied,
+            level: entry.md_price_level,
+            price: entry.md_entry_px.clone(),
+            size: entry.md_entry_size,
+            time,
+            sending_time,
+        });
+    }
+    match entry_type {
+        EntryType::Trade => MarketEvent::Trade(TradeEvent {
+            security_id: entry.security_id,
+            rpt_seq: entry.rpt_seq,
+            trade_date,
+            price: entry.md_entry_px.clone(),
+            size: entry.md_entry_size,
+            aggressor: entry.aggressor_side.map(Aggressor::from_fix),
+            trade_volume: entry.trade_volume,
+            workup_state: entry.md_workup_state,
+            trade_condition: entry.trade_condition.clone(),
+            party_ids: entry.parties.iter().flatten().map(|p| p.party_id).collect(),
+            time,
+            sending_time,
+        }),
+        EntryType::EmptyBook => MarketEvent::SessionEvent(SessionEvent::EmptyBook {
+            security_id: entry.security_id,
+            sending_time,
+        }),
+        entry_type => MarketEvent::Statistic(Statistic {
+            security_id: entry.security_id,
+            rpt_seq: Some(entry.rpt_seq),
+            kind: statistic_kind(&entry_type, &entry.md_entry_type),
+            trade_date,
+            price: entry.md_entry_px.clone(),
+            size: entry.md_entry_size,
+            quote_type: entry.md_quote_type,
+            time,
+            sending_time,
+        }),
+    }
+}
+
+fn normalize_snapshot(msg: &SnapshotFullRefresh, events: &mut Vec<MarketEvent>) {
+    let sending_time = utc_timestamp(msg.msg_header.sending_time);
+    events.push(MarketEvent::SessionEvent(SessionEvent::Snapshot {
+        security_id: msg.security_id,
+        rpt_seq: msg.rpt_seq,
+        last_msg_seq_num_processed: msg.last_msg_seq_num_processed,
+        sending_time,
+    }));
+    if let Some(status) = msg.md_security_trading_status {
+        events.push(MarketEvent::StatusChange(StatusChange {
+            security_id: Some(msg.security_id),
+            symbol: None,
+            status: TradingStatus::from_fix(status),
+            sending_time,
+        }));
+    }
+    for entry in &msg.md_entries {
+        if let Some(event) = normalize_snapshot_entry(msg.security_id, entry, sending_time) {
+            events.push(event);
+        }
+    }
+}
+
+fn normalize_snapshot_entry(
+    security_id: u32,
+    entry: &MDEntrySnapshot,
+    sending_time: Option<DateTime<Utc>>,
+) -> Option<MarketEvent> {
+    let entry_type = EntryType::from_fix(&entry.md_entry_type);
+    if let Some((side, implied)) = book_side(&entry_type) {
+        return Some(MarketEvent::BookUpdate(BookUpdate {
+            security_id,
+            rpt_seq: None,
+            action: UpdateAction::New,
+            side,
+            implied,
+            level: entry.md_price_level,
+            price: entry.md_entry_px.clone(),
+            size: entry.md_entry_size,
+            time: None,
+            sending_time,
+        }));
+    }
+    match entry_type {
+        // last trade in the snapshot is a state, not a new trade
+        EntryType::Trade | EntryType::EmptyBook => None,
+        entry_type => Some(MarketEvent::Statistic(Statistic {
+            security_id,
+            rpt_seq: None,
+            kind: statistic_kind(&entry_type, &entry.md_entry_type),
+            trade_date: None,
+            price: entry.md_entry_px.clone(),
+            size: entry.md_entry_size,
+            quote_type: None,
+            time: None,
+            sending_time,
+        })),
+    }
+}
+
+fn normalize_status(msg: &SecurityStatus, events: &mut Vec<MarketEvent>) {
+    if let Some(status) = msg.security_trading_status {
+        events.push(MarketEvent::StatusChange(StatusChange {
+            security_id: msg.security_id,
+            symbol: msg.symbol.clone(),
+            status: TradingStatus::from_fix(status),
+            sending_time: utc_timestamp(msg.msg_header.sending_time),
+        }));
+    }
+}
+
+fn book_side(entry_type: &EntryType) -> Option<(Side, bool)> {
+    match entry_type {
+        EntryType::Bid => Some((Side::Bid, false)),
+        EntryType::Offer => Some((Side::Offer, false)),
+        EntryType::ImpliedBid => Some((Side::Bid, true)),
+        EntryType::ImpliedOffer => Some((Side::Offer, true)),
+        _ => None,
+    }
+}
+
+fn statistic_kind(entry_type: &EntryType, raw: &str) -> StatisticKind {
+    match entry_type {
+        EntryType::OpeningPrice => StatisticKind::OpeningPrice,
+        EntryType::SettlementPrice => StatisticKind::SettlementPrice,
+        EntryType::SessionHigh => StatisticKind::SessionHigh,
+        EntryType::SessionLow => StatisticKind::SessionLow,
+        EntryType::ClearedVolume => StatisticKind::ClearedVolume,
+        EntryType::OpenInterest => StatisticKind::OpenInterest,
+        _ => StatisticKind::Other(raw.to_string()),
+    }
+}
+
+/// Compare decimals by value regardless of their exponents.
+#[must_use]
+pub fn cmp_decimal(a: &Decimal, b: &Decimal) -> Ordering {
+    let exponent = a.exponent.min(b.exponent);
+    let scaled = |d: &Decimal| {
+        10i128
+            .checked_pow((d.exponent - exponent).unsigned_abs())
+            .and_then(|scale| i128::from(d.mantissa).checked_mul(scale))
+    };
+    match (scaled(a), scaled(b)) {
+        (Some(a), Some(b)) => a.cmp(&b),
+        _ => f64::from(a.clone()).total_cmp(&f64::from(b.clone())),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::fast::Party;
+    use crate::fast::fixtures::{header, md_entry};
+
+    fn entry(entry_type: &str, price: i64, size: i32) -> MDEntry {
+        MDEntry {
+            md_entry_type: entry_type.to_string(),
+            md_entry_size: Some(size),
+            ..md_entry(100, 7, Some(Decimal::new(-2, price)))
+        }
+    }
+
+    #[test]
+    fn test_inc_refresh() {
+        let mut trade = entry("2", 450025, 3);
+        trade.aggressor_side = Some(1);
+        trade.trade_volume = Some(1200);
+        trade.parties = Some(vec![Party {
+            party_id: 42,
+            party_id_source: "D".to_string(),
+        }]);
+        let msg = Message::MDIncRefresh(IncRefresh {
+            message_type: "X".to_string(),
+            msg_header: header(1),
+            trade_date: Some(20250620),
+            md_entries: vec![
+                entry("E", 450000, 10),
+                trade,
+                entry("C", 0, 5000),
+                entry("J", 0, 0),
+            ],
+        });
+        let events = normalize(&msg);
+        assert_eq!(events.len(), 4);
+
+        let MarketEvent::BookUpdate(update) = &events[0] else {
+            panic!("expected book update");
+        };
+        assert_eq!(update.side, Side::Bid);
+        assert!(update.implied);
+        assert_eq!(update.action, UpdateAction::New);
+        assert_eq!(update.price, Some(Decimal::new(-2, 450000)));
+        assert_eq!(
+            update.time.unwrap().to_rfc3339(),
+            "2025-06-20T10:22:47.123+00:00"
+        );
+
+        let MarketEvent::Trade(trade) = &events[1] else {
+            panic!("expected trade");
+        };
+        assert_eq!(trade.aggressor, Some(Aggressor::Buy));
+        assert_eq!(trade.size, Some(3));
+        assert_eq!(trade.party_ids, vec![42]);
+        assert_eq!(trade.trade_date, NaiveDate::from_ymd_opt(2025, 6, 20));
+
+        let MarketEvent::Statistic(stat) = &events[2] else {
+            panic!("expected statistic");
+        };
+        assert_eq!(stat.kind, StatisticKind::OpenInterest);
+        assert_eq!(stat.size, Some(5000));
+
+        assert!(matches!(
+            events[3],
+            MarketEvent::SessionEvent(SessionEvent::EmptyBook {
+                security_id: 100,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_snapshot() {
+        let msg = Message::MDSnapshotFullRefresh(SnapshotFullRefresh {
+            message_type: "W".to_string(),
+            msg_header: header(1),
+            last_msg_seq_num_processed: 10,
+            tot_num_reports: 1,
+            rpt_seq: 7,
+            security_id: 100,
+            security_id_source: 100,
+            md_security_trading_status: Some(17),
+            md_entries: vec![
+                MDEntrySnapshot {
+                    md_entry_type: "1".to_string(),
+                    md_entry_px: Some(Decimal::new(-2, 450050)),
+                    md_entry_size: Some(4),
+                    quote_condition: None,
+                    md_price_level: Some(1),
+                    md_workup_state: None,
+                },
+                MDEntrySnapshot {
+                    md_entry_type: "2".to_string(),
+                    md_entry_px: Some(Decimal::new(-2, 450025)),
+                    md_entry_size: Some(1),
+                    quote_condition: None,
+                    md_price_level: None,
+                    md_workup_state: None,
+                },
+            ],
+        });
+        let events = normalize(&msg);
+        assert_eq!(events.len(), 3);
+        assert!(matches!(
+            events[0],
+            MarketEvent::SessionEvent(SessionEvent::Snapshot { rpt_seq: 7, .. })
+        ));
+        assert!(matches!(
+            &events[1],
+            MarketEvent::StatusChange(StatusChange {
+                status: TradingStatus::ReadyToTrade,
+                ..
+            })
+        ));
+        assert!(matches!(
+            &events[2],
+            MarketEvent::BookUpdate(BookUpdate {
+                side: Side::Offer,
+                implied: false,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_cmp_decimal() {
+        assert_eq!(
+            cmp_decimal(&Decimal::new(-2, 100), &Decimal::new(0, 1)),
+            Ordering::Equal
+        );
+        assert_eq!(
+            cmp_decimal(&Decimal::new(-1, 15), &Decimal::new(0, 1)),
+            Ordering::Greater
+        );
+    }
+}
@@ -0,0 +1,581 @@
+//! # News
+//!
+//! Reassembles `News` fragments into stories, decodes headline and text using
+//! the declared `MessageEncoding` and keeps stories in a searchable in-memory store.
+//!
+//! A story may be split over several messages with the same `NewsID`;
+//! the story is complete once the message with `LastFragment` is received.
+//! Updates reference earlier stories through `NewsRefIDs`.
+//!
+//! Stories that never receive their last fragment are dropped, oldest first,
+//! once more than [`NewsStore::with_max_pending`] stories are incomplete.
+//! Stories that fail to decode are set aside until sent again, within the same limit.
+//!
+//! # Examples
+//!
+//! ```rust,ignore
+//! use quotesdirectlib::news::{NewsFilter, NewsStore};
+//!
+//! let mut store = NewsStore::new();
+//! if let Some(story) = store.process(&msg)? {
+//!     println!("{:?} {}", story.orig_time, story.headline);
+//! }
+//! let filter = NewsFilter::new().with_text("crude").with_category(1, "EN");
+//! for story in store.search(&filter) {
+//!     println!("{}", story.headline);
+//! }
+//! ```
+//!
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+
+use crate::fast::{Message, News, NewsCategory};
+use crate::time::utc_timestamp;
+use crate::{Error, Result};
+
+/// `NewsRefType` (1477) values.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum NewsRefType {
+    /// The story replaces the referenced one.
+    Replacement,
+    /// The story is the referenced one in other language.
+    OtherLanguage,
+    /// The story complements the referenced one.
+    Complimentary,
+    Other(u32),
+}
+
+impl NewsRefType {
+    #[must_use]
+    pub fn from_fix(value: u32) -> Self {
+        match value {
+            0 => NewsRefType::Replacement,
+            1 => NewsRefType::OtherLanguage,
+            2 => NewsRefType::Complimentary,
+            _ => NewsRefType::Other(value),
+        }
+    }
+}
+
+/// Reference to an earlier story.
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+pub struct NewsRef {
+    pub news_id: String,
+    pub ref_type: NewsRefType,
+}
+
+/// Complete news story.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Story {
+    pub news_id: String,
+    pub news_source_id: u32,
+    pub appl_id: String,
+    /// `OrigTime`, the `SendingTime` of the first fragment if not published.
+    pub orig_time: Option<DateTime<Utc>>,
+    pub urgency: Option<String>,
+    pub news_branding: Option<String>,
+    pub accession_number: Option<String>,
+    pub headline: String,
+    pub text: String,
+    pub refs: Vec<NewsRef>,
+    pub categories: Vec<NewsCategory>,
+}
+
+impl Story {
+    /// The story has the category.
+    #[must_use]
+    pub fn has_category(&self, category_class: u32, category_code: &str) -> bool {
+        self.categories
+            .iter()
+            .any(|c| c.category_class == category_class && c.category_code == category_code)
+    }
+}
+
+/// Decode text in the `MessageEncoding`.
+/// Invalid sequences are replaced with `U+FFFD`.
+///
+/// # Errors
+///
+/// Returns [`Error::UnsupportedEncoding`] if the encoding is not one of
+/// UTF-8, ASCII, ISO-8859-1 and UTF-16 (LE/BE).
+pub fn decode_text(encoding: &str, bytes: &[u8]) -> Result<String> {
+    let utf16 = |to_u16: fn([u8; 2]) -> u16| {
+        let units: Vec<u16> = bytes
+            .chunks_exact(2)
+            .map(|c| to_u16([c[0], c[1]]))
+            .collect();
+        String::from_utf16_lossy(&units)
+    };
+    match encoding.to_ascii_uppercase().as_str() {
+        "" | "UTF-8" | "UTF8" => Ok(String::from_utf8_lossy(bytes).into_owned()),
+        "ASCII" | "US-ASCII" => Ok(bytes
+            .iter()
+            .map(|b| {
+                if b.is_ascii() {
+                    char::from(*b)
+                } else {
+                    '\u{fffd}'
+                }
+            })
+            .collect()),
+        "ISO-8859-1" | "LATIN-1" | "LATIN1" => Ok(bytes.iter().map(|b| char::from(*b)).collect()),
+        "UTF-16LE" => Ok(utf16(u16::from_le_bytes)),
+        "UTF-16" | "UTF-16BE" => Ok(utf16(u16::from_be_bytes)),
+        _ => Err(Error::UnsupportedEncoding(encoding.to_string())),
+    }
+}
+
+/// Default limit of incomplete stories.
+pub const DEFAULT_MAX_PENDING: usize = 1024;
+
+/// Fragments of a story being received.
+#[derive(Debug)]
+struct Pending {
+    /// Arrival order of the first fragment.
+    order: u64,
+    first: News,
+    headline: Vec<u8>,
+    text: Vec<u8>,
+}
+
+/// Selects stories in [`NewsStore::search`]. All set criteria must match.
+#[derive(Debug, Default, Clone)]
+pub struct NewsFilter {
+    text: Option<String>,
+    categories: Vec<(u32, String)>,
+    news_source_id: Option<u32>,
+    from: Option<DateTime<Utc>>,
+    to: Option<DateTime<Utc>>,
+}
+
+impl NewsFilter {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Headline or text contains the words, case insensitive.
+    #[must_use]
+    pub fn with_text(mut self, text: &str) -> Self {
+        self.text = Some(text.to_lowercase());
+        self
+    }
+
+    /// The story has the category. May be given several times.
+    #[must_use]
+    pub fn with_category(mut self, category_class: u32, category_code: &str) -> Self {
+        self.categories
+            .push((category_class, category_code.to_string()));
+        self
+    }
+
+    #[must_use]
+    pub fn with_source(mut self, news_source_id: u32) -> Self {
+        self.news_source_id = Some(news_source_id);
+        self
+    }
+
+    /// Story time in `[from, to)`.
+    #[must_use]
+    pub fn between(mut self, from: DateTime<Utc>, to: DateTime<Utc>) -> Self {
+        self.from = Some(from);
+        self.to = Some(to);
+        self
+    }
+
+    #[must_use]
+    pub fn matches(&self, story: &Story) -> bool {
+        let text = self.text.as_ref().is_none_or(|text| {
+            story.headline.to_lowercase().contains(text) || story.text.to_lowercase().contains(text)
+        });
+        let categories = self
+            .categories
+            .iter()
+            .all(|(class, code)| story.has_category(*class, code));
+        let source = self
+            .news_source_id
+            .is_none_or(|id| id == story.news_source_id);
+        let time = match (self.from, self.to) {
+            (None, None) => true,
+            (from, to) => story.orig_time.is_some_and(|time| {
+                from.is_none_or(|from| from <= time) && to.is_none_or(|to| time < to)
+            }),
+        };
+        text && categories && source && time
+    }
+}
+
+/// Complete stories in arrival order.
+#[derive(Debug)]
+pub struct NewsStore {
+    stories: Vec<Story>,
+    /// Index in `stories` by `NewsID`.
+    index: HashMap<String, usize>,
+    pending: HashMap<String, Pending>,
+    /// Complete stories that failed to decode.
+    failed: HashMap<String, Pending>,
+    max_pending: usize,
+    next_order: u64,
+    dropped: u64,
+    failures: u64,
+}
+
+impl Default for NewsStore {
+    fn default() -> Self {
+        Self {
+            stories: Vec::new(),
+            index: HashMap::new(),
+            pending: HashMap::new(),
+            failed: HashMap::new(),
+            max_pending: DEFAULT_MAX_PENDING,
+            next_order: 0,
+            dropped: 0,
+            failures: 0,
+        }
+    }
+}
+
+impl NewsStore {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Maximum number of incomplete stories kept, [`DEFAULT_MAX_PENDING`] by default.
+    /// Stories that failed to decode are kept within the same limit.
+    #[must_use]
+    pub fn with_max_pending(mut self, max_pending: usize) -> Self {
+        self.max_pending = max_pending;
+        self
+    }
+
+    /// Add a news message. Returns the story once its last fragment is received.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::UnsupportedEncoding`] if the story text cannot be decoded.
+    pub fn process(&mut self, msg: &Message) -> Result<Option<&Story>> {
+        match msg {
+            Message::News(news) => self.add(news),
+            _ => Ok(None),
+        }
+    }
+
+    /// Add a news fragment. Returns the story once its last fragment is received.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::UnsupportedEncoding`] if the story text cannot be decoded.
+    /// The fragments are kept until the story is sent again, see [`NewsStore::raw`].
+    pub fn add(&mut self, news: &News) -> Result<Option<&Story>> {
+        if !self.pending.contains_key(&news.news_id) {
+            self.evict_pending();
+        }
+        let order = self.next_order;
+        let pending = self
+            .pending
+            .entry(news.news_id.clone())
+            .or_insert_with(|| Pending {
+                order,
+                first: news.clone(),
+                headline: Vec::new(),
+                text: Vec::new(),
+            });
+        if pending.order == order {
+            self.next_order += 1;
+        }
+        pending
+            .headline
+            .extend(news.encoded_headline.iter().flatten());
+        pending.text.extend(news.encoded_text.iter().flatten());
+        if news.last_fragment.as_deref() != Some("Y") {
+            return Ok(None);
+        }
+        let Some(pending) = self.pending.remove(&news.news_id) else {
+            return Ok(None);
+        };
+        let encoding = &pending.first.message_encoding;
+        let decoded = decode_text(encoding, &pending.headline)
+            .and_then(|headline| Ok((headline, decode_text(encoding, &pending.text)?)));
+        let (headline, text) = match decoded {
+            Ok(decoded) => decoded,
+            Err(err) => {
+                self.fail(pending);
+                return Err(err);
+            }
+        };
+        self.failed.remove(&news.news_id);
+        let first = pending.first;
+        let story = Story {
+            headline,
+            text,
+            orig_time: first
+                .orig_time
+                .or(Some(first.msg_header.sending_time))
+                .and_then(utc_timestamp),
+            refs: first
+                .news_ref_ids
+                .iter()
+                .flatten()
+                .map(|r| NewsRef {
+                    news_id: r.news_ref_id.clone(),
+                    ref_type: NewsRefType::from_fix(r.news_ref_type),
+                })
+                .collect(),
+            categories: first.news_categories.unwrap_or_default(),
+            news_id: first.news_id,
+            news_source_id: first.news_source_id,
+            appl_id: first.appl_id,
+            urgency: first.urgency,
+            news_branding: first.news_branding,
+            accession_number: first.accession_number,
+        };
+        // a story sent again with the same `NewsID` replaces the stored one
+        let next = self.stories.len();
+        let index = *self.index.entry(story.news_id.clone()).or_insert(next);
+        if index == next {
+            self.stories.push(story);
+        } else {
+            self.stories[index] = story;
+        }
+        Ok(self.stories.get(index))
+    }
+
+    #[must_use]
+    pub fn get(&self, news_id: &str) -> Option<&Story> {
+        self.stories.get(*self.index.get(news_id)?)
+    }
+
+    /// Stories referencing the story.
+    pub fn updates<'a>(&'a self, news_id: &'a str) -> impl Iterator<Item = &'a Story> {
+        self.stories
+            .iter()
+            .filter(move |story| story.refs.iter().any(|r| r.news_id == news_id))
+    }
+
+    /// The latest version of the story following replacements.
+    #[must_use]
+    pub fn latest(&self, news_id: &str) -> Option<&Story> {
+        let mut story = self.get(news_id)?;
+        // a replacement chain cannot be longer than the store
+        for _ in 0..self.stories.len() {
+            let replacement = self.updates(&story.news_id).find(|s| {
+                s.refs
+                    .iter()
+                    .any(|r| r.news_id == story.news_id && r.ref_type == NewsRefType::Replacement)
+            });
+            match replacement {
+                Some(replacement) => story = replacement,
+                None => break,
+            }
+        }
+        Some(story)
+    }
+
+    /// Stories matching the filter in arrival order.
+    pub fn search<'a>(&'a self, filter: &'a NewsFilter) -> impl Iterator<Item = &'a Story> {
+        self.stories.iter().filter(|story| filter.matches(story))
+    }
+
+    /// All stories in arrival order.
+    pub fn iter(&self) -> impl Iterator<Item = &Story> {
+        self.stories.iter()
+    }
+
+    /// Number of stories with fragments still missing.
+    #[must_use]
+    pub fn pending(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Encoded headline and text received so far of an incomplete or undecodable story.
+    #[must_use]
+    pub fn raw(&self, news_id: &str) -> Option<(&[u8], &[u8])> {
+        let pending = self
+            .pending
+            .get(news_id)
+            .or_else(|| self.failed.get(news_id))?;
+        Some((&pending.headline, &pending.text))
+    }
+
+    /// `NewsID`s of stories that failed to decode and were not sent again since.
+    pub fn failed(&self) -> impl Iterator<Item = &str> {
+        self.failed.keys().map(String::as_str)
+    }
+
+    /// Number of incomplete stories dropped to stay within the limit.
+    #[must_use]
+    pub fn dropped(&self) -> u64 {
+        self.dropped
+    }
+
+    /// Number of stories that failed to decode, including retransmissions.
+    #[must_use]
+    pub fn failures(&self) -> u64 {
+        self.failures
+    }
+
+    /// Drop the oldest incomplete stories to make room for a new one.
+    fn evict_pending(&mut self) {
+        while self.pending.len() >= self.max_pending.max(1) {
+            let Some(oldest) = oldest(&self.pending) else {
+                break;
+            };
+            self.pending.remove(&oldest);
+            self.dropped += 1;
+        }
+    }
+
+    /// Set aside a story that failed to decode, replacing an earlier failure of the story.
+    fn fail(&mut self, story: Pending) {
+        self.failures += 1;
+        if !self.failed.contains_key(&story.first.news_id) {
+            while self.failed.len() >= self.max_pending.max(1) {
+                let Some(oldest) = oldest(&self.failed) else {
+                    break;
+                };
+                self.failed.remove(&oldest);
+            }
+        }
+        self.failed.insert(story.first.news_id.clone(), story);
+    }
+}
+
+/// `NewsID` of the story received first.
+fn oldest(stories: &HashMap<String, Pending>) -> Option<String> {
+    stories
+        .iter()
+        .min_by_key(|(_, p)| p.order)
+        .map(|(news_id, _)| news_id.clone())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::fast::NewsRefID;
+    use crate::fast::fixtures::header;
+
+    fn news(news_id: &str, headline: &str, text: &[u8], last: bool) -> News {
+        News {
+            message_type: "B".to_string(),
+            msg_header: header(1),
+            message_encoding: "UTF-8".to_string(),
+            appl_id: "N".to_string(),
+            news_id: news_id.to_string(),
+            news_source_id: 1,
+            last_fragment: last.then(|| "Y".to_string()),
+            news_ref_ids: None,
+            orig_time: None,
+            urgency: None,
+            news_branding: None,
+            accession_number: None,
+            encoded_headline: (!headline.is_empty()).then(|| headline.as_bytes().to_vec()),
+            encoded_text: Some(text.to_vec()),
+            news_categories: Some(vec![NewsCategory {
+                category_class: 1,
+                category_code: "EN".to_string(),
+            }]),
+        }
+    }
+
+    #[test]
+    fn test_decode() {
+        assert_eq!(decode_text("UTF-8", "Öl".as_bytes()).unwrap(), "Öl");
+        assert_eq!(decode_text("ISO-8859-1", &[0xd6, b'l']).unwrap(), "Öl");
+        assert_eq!(decode_text("UTF-16LE", &[0xd6, 0, b'l', 0]).unwrap(), "Öl");
+        assert!(decode_text("EBCDIC", b"").is_err());
+    }
+
+    #[test]
+    fn test_fragments() {
+        let mut store = NewsStore::new();
+        let text = "Crude Öl".as_bytes();
+        // the multi-byte character is split between fragments
+        assert!(
+            store
+                .add(&news("1", "Crude", &text[..7], false))
+                .unwrap()
+                .is_none()
+        );
+        assert_eq!(store.pending(), 1);
+        let story = store
+            .add(&news("1", "", &text[7..], true))
+            .unwrap()
+            .unwrap();
+        assert_eq!(story.headline, "Crude");
+        assert_eq!(story.text, "Crude Öl");
+        assert_eq!(story.orig_time, utc_timestamp(20250620102247123));
+        assert_eq!(store.pending(), 0);
+    }
+
+    #[test]
+    fn test_decode_error_keeps_fragments() {
+        let mut store = NewsStore::new();
+        let mut fragment = news("1", "Crude", b"up", true);
+        fragment.message_encoding = "EBCDIC".to_string();
+        assert!(store.add(&fragment).is_err());
+        assert_eq!(store.pending(), 0);
+        assert_eq!(store.raw("1"), Some((&b"Crude"[..], &b"up"[..])));
+        assert!(store.get("1").is_none());
+
+        // a retransmission replaces the failed story
+        assert!(store.add(&fragment).is_err());
+        assert_eq!(store.raw("1"), Some((&b"Crude"[..], &b"up"[..])));
+        assert_eq!(store.failed().collect::<Vec<_>>(), vec!["1"]);
+        assert_eq!((store.failures(), store.dropped()), (2, 0));
+
+        fragment.message_encoding = "UTF-8".to_string();
+        assert_eq!(store.add(&fragment).unwrap().unwrap().text, "up");
+        assert!(store.raw("1").is_none());
+        assert_eq!(store.failed().count(), 0);
+    }
+
+    #[test]
+    fn test_max_pending() {
+        let mut store = NewsStore::new().with_max_pending(2);
+        store.add(&news("1", "One", b"", false)).unwrap();
+        store.add(&news("2", "Two", b"", false)).unwrap();
+        store.add(&news("1", "", b"more", false)).unwrap();
+        store.add(&news("3", "Three", b"", false)).unwrap();
+        assert_eq!(store.pending(), 2);
+        assert_eq!(store.dropped(), 1);
+        // the oldest story is dropped even though it received a later fragment
+        assert!(store.raw("1").is_none());
+        assert_eq!(
+            store
+                .add(&news("2", "", b"", true))
+                .unwrap()
+                .unwrap()
+                .headline,
+            "Two"
+        );
+    }
+
+    #[test]
+    fn test_updates() {
+        let mut store = NewsStore::new();
+        store.add(&news("1", "Crude up", b"", true)).unwrap();
+        let mut update = news("2", "Crude down", b"corrected", true);
+        update.news_ref_ids = Some(vec![NewsRefID {
+            news_ref_id: "1".to_string(),
+            news_ref_type: 0,
+        }]);
+        update.news_categories = None;
+        store.add(&update).unwrap();
+
+        assert_eq!(store.updates("1").count(), 1);
+        assert_eq!(store.latest("1").unwrap().news_id, "2");
+        assert_eq!(store.latest("2").unwrap().news_id, "2");
+
+        let filter = NewsFilter::new().with_text("CRUDE");
+        assert_eq!(store.search(&filter).count(), 2);
+        let filter = filter.with_category(1, "EN");
+        assert_eq!(
+            store
+                .search(&filter)
+                .map(|s| s.news_id.as_str())
+                .collect::<Vec<_>>(),
+            vec!["1"]
+        );
+    }
+}
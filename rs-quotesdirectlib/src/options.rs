@@ -0,0 +1,299 @@
+//! # Options chains
+//!
+//! Groups option definitions by underlying `SecurityID` and maturity,
+//! pairs calls and puts of the same strike and keeps strikes sorted.
+//!
+//! # Examples
+//!
+//! ```rust,ignore
+//! use quotesdirectlib::options::OptionChains;
+//!
+//! let chains = OptionChains::from_definitions(master.iter());
+//! for chain in chains.chains_for(underlying_id) {
+//!     let underlying = chain.underlying_book(&books);
+//!     for row in chain.strikes() {
+//!         println!("{} {:?} {:?}", row.strike, row.call, row.put);
+//!     }
+//! }
+//! ```
+//!
+use chrono::{DateTime, Utc};
+use fastlib::Decimal;
+use std::collections::{BTreeMap, HashMap};
+
+use crate::book::{OrderBook, OrderBooks};
+use crate::cfi::Cfi;
+use crate::fast::SecurityDefinition;
+use crate::market::cmp_decimal;
+
+/// Option type.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum OptionRight {
+    Call,
+    Put,
+}
+
+impl OptionRight {
+    /// Option type from the CFI code, `None` if the instrument is not a listed option.
+    #[must_use]
+    pub fn from_cfi(cfi_code: &str) -> Option<Self> {
+        Cfi::parse(cfi_code).option_right()
+    }
+}
+
+/// Call and put of one strike.
+#[derive(Debug, PartialEq, Clone)]
+pub struct StrikeRow {
+    pub strike: Decimal,
+    /// `SecurityID` of the call.
+    pub call: Option<u32>,
+    /// `SecurityID` of the put.
+    pub put: Option<u32>,
+}
+
+/// Options on one underlying with the same maturity.
+#[derive(Debug, PartialEq, Clone)]
+pub struct OptionChain {
+    pub underlying_id: u32,
+    /// `MaturityMonthYear` of the options.
+    pub maturity: u64,
+    /// Expiration time of the options, if published.
+    pub expiration: Option<DateTime<Utc>>,
+    strikes: Vec<StrikeRow>,
+}
+
+impl OptionChain {
+    fn new(underlying_id: u32, maturity: u64) -> Self {
+        Self {
+            underlying_id,
+            maturity,
+            expiration: None,
+            strikes: Vec::new(),
+        }
+    }
+
+    /// Strikes in ascending order.
+    #[must_use]
+    pub fn strikes(&self) -> &[StrikeRow] {
+        &self.strikes
+    }
+
+    #[must_use]
+    pub fn strike(&self, strike: &Decimal) -> Option<&StrikeRow> {
+        let index = self.find(strike).ok()?;
+        Some(&self.strikes[index])
+    }
+
+    /// Live order book of the underlying if tracked.
+    #[must_use]
+    pub fn underlying_book<'a>(&self, books: &'a OrderBooks) -> Option<&'a OrderBook> {
+        books.get(self.underlying_id)
+    }
+
+    fn find(&self, strike: &Decimal) -> Result<usize, usize> {
+        self.strikes
+            .binary_search_by(|row| cmp_decimal(&row.strike, strike))
+    }
+
+    fn insert(&mut self, strike: &Decimal, right: OptionRight, security_id: u32) {
+        let index = match self.find(strike) {
+            Ok(index) => index,
+            Err(index) => {
+                let row = StrikeRow {
+                    strike: strike.clone(),
+                    call: None,
+                    put: None,
+                };
+                self.strikes.insert(index, row);
+                index
+            }
+        };
+        let row = &mut self.strikes[index];
+        match right {
+            OptionRight::Call => row.call = Some(security_id),
+            OptionRight::Put => row.put = Some(security_id),
+        }
+    }
+
+    fn remove(&mut self, strike: &Decimal, security_id: u32) {
+        let Ok(index) = self.find(strike) else {
+            return;
+        };
+        let row = &mut self.strikes[index];
+        for id in [&mut row.call, &mut row.put] {
+            if *id == Some(security_id) {
+                *id = None;
+            }
+        }
+        if row.call.is_none() && row.put.is_none() {
+            self.strikes.remove(index);
+        }
+    }
+}
+
+/// Options chains by underlying and maturity.
+#[derive(Debug, Default)]
+pub struct OptionChains {
+    chains: BTreeMap<(u32, u64), OptionChain>,
+    /// Chain key and strike by option's `SecurityID`.
+    options: HashMap<u32, ((u32, u64), Decimal)>,
+}
+
+impl OptionChains {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Build chains from security definitions, skipping non-options.
+    pub fn from_definitions<'a>(
+        definitions: impl IntoIterator<Item = &'a SecurityDefinition>,
+    ) -> Self {
+        let mut chains = Self::new();
+        for definition in definitions {
+            chains.add(definition);
+        }
+        chains
+    }
+
+    /// Add an option to its chain. Returns `false` if the definition is not an option
+    /// or lacks strike, maturity or underlying.
+    /// An updated definition replaces the option's previous chain and strike,
+    /// or removes the option if it no longer qualifies.
+    pub fn add(&mut self, definition: &SecurityDefinition) -> bool {
+        self.remove(definition.security_id);
+        let (Some(right), Some(strike), Some(maturity), Some(underlying)) = (
+            OptionRight::from_cfi(&definition.cfi_code),
+            &definition.strike_price,
+            definition.maturity_month_year,
+            definition.underlyings.iter().flatten().next(),
+        ) else {
+            return false;
+        };
+        let key = (underlying.security_id, maturity);
+        let chain = self
+            .chains
+            .entry(key)
+            .or_insert_with(|| OptionChain::new(underlying.security_id, maturity));
+        chain.insert(strike, right, definition.security_id);
+        self.options
+            .insert(definition.security_id, (key, strike.clone()));
+        chain.expiration = chain.expiration.or_else(|| definition.expiration_time());
+        true
+    }
+
+    /// Remove an option from its chain, dropping chains left empty.
+    pub fn remove(&mut self, security_id: u32) -> bool {
+        let Some((key, strike)) = self.options.remove(&security_id) else {
+            return false;
+        };
+        if let Some(chain) = self.chains.get_mut(&key) {
+            chain.remove(&strike, security_id);
+            if chain.strikes.is_empty() {
+                self.chains.remove(&key);
+            }
+        }
+        true
+    }
+
+    #[must_use]
+    pub fn chain(&self, underlying_id: u32, maturity: u64) -> Option<&OptionChain> {
+        self.chains.get(&(underlying_id, maturity))
+    }
+
+    /// Chains on the underlying ordered by maturity.
+    pub fn chains_for(&self, underlying_id: u32) -> impl Iterator<Item = &OptionChain> {
+        self.chains
+            .range((underlying_id, 0)..=(underlying_id, u64::MAX))
+            .map(|(_, chain)| chain)
+    }
+
+    /// All chains ordered by underlying and maturity.
+    pub fn iter(&self) -> impl Iterator<Item = &OptionChain> {
+        self.chains.values()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::fast::Underlying;
+    use crate::fast::fixtures::definition;
+
+    fn option(security_id: u32, cfi_code: &str, strike: i64, maturity: u64) -> SecurityDefinition {
+        SecurityDefinition {
+            cfi_code: cfi_code.to_string(),
+            strike_price: Some(Decimal::new(0, strike)),
+            maturity_month_year: Some(maturity),
+            underlyings: Some(vec![Underlying {
+                security_id: 100,
+                security_id_source: 8,
+            }]),
+            ..definition(security_id)
+        }
+    }
+
+    #[test]
+    fn test_chains() {
+        let definitions = [
+            option(1, "OCAFPS", 6000, 202509),
+            option(2, "OPAFPS", 6000, 202509),
+            option(3, "OCAFPS", 5900, 202509),
+            option(4, "OPAFPS", 6000, 202512),
+            option(5, "FFIXSX", 0, 202509),
+        ];
+        let mut chains = OptionChains::from_definitions(&definitions);
+        assert_eq!(chains.chains_for(100).count(), 2);
+        assert_eq!(chains.chains_for(5).count(), 0);
+
+        let chain = chains.chain(100, 202509).unwrap();
+        assert_eq!(
+            chain
+                .strikes()
+                .iter()
+                .map(|r| r.strike.mantissa)
+                .collect::<Vec<_>>(),
+            vec![5900, 6000]
+        );
+        let row = chain.strike(&Decimal::new(2, 60)).unwrap();
+        assert_eq!((row.call, row.put), (Some(1), Some(2)));
+
+        assert!(chains.remove(4));
+        assert!(chains.chain(100, 202512).is_none());
+        assert!(!chains.remove(5));
+
+        let mut books = OrderBooks::new();
+        books.track(100);
+        let chain = chains.chain(100, 202509).unwrap();
+        assert_eq!(chain.underlying_book(&books).unwrap().security_id(), 100);
+    }
+
+    #[test]
+    fn test_update_definition() {
+        let mut chains = OptionChains::from_definitions(&[
+            option(1, "OCAFPS", 6000, 202509),
+            option(2, "OPAFPS", 6000, 202509),
+        ]);
+        assert!(chains.add(&option(1, "OCAFPS", 6100, 202509)));
+
+        let chain = chains.chain(100, 202509).unwrap();
+        assert_eq!(chain.strikes().len(), 2);
+        let row = chain.strike(&Decimal::new(2, 60)).unwrap();
+        assert_eq!((row.call, row.put), (None, Some(2)));
+        let row = chain.strike(&Decimal::new(2, 61)).unwrap();
+        assert_eq!((row.call, row.put), (Some(1), None));
+
+        // moved to another maturity, the empty strike is dropped
+        assert!(chains.add(&option(2, "OPAFPS", 6000, 202512)));
+        let chain = chains.chain(100, 202509).unwrap();
+        assert_eq!(chain.strikes().len(), 1);
+        assert!(chains.chain(100, 202512).is_some());
+
+        // re-sent without the strike, the option is removed
+        let mut no_strike = option(2, "OPAFPS", 6000, 202512);
+        no_strike.strike_price = None;
+        assert!(!chains.add(&no_strike));
+        assert!(chains.chain(100, 202512).is_none());
+        assert!(!chains.remove(2));
+    }
+}
@@ -0,0 +1,161 @@
+//! # Instrument recovery
+//!
+//! Tracks instruments whose state may be stale after a decode error.
+//!
+//! Messages discarded after a decode error cannot be attributed to instruments,
+//! so every instrument seen on the feed is marked as needing recovery.
+//! An instrument is recovered when its `SnapshotFullRefresh` is received.
+//!
+//! # Examples
+//!
+//! ```rust,ignore
+//! use quotesdirectlib::recovery::RecoveryTracker;
+//!
+//! let mut recovery = RecoveryTracker::new();
+//! let decoded = decoder.decode_packet_recovering(packet.payload);
+//! for msg in &decoded.messages {
+//!     recovery.process(msg);
+//! }
+//! if decoded.error.is_some() {
+//!     recovery.mark_all();
+//! }
+//! if recovery.needs_recovery(security_id) {
+//!     // wait for the snapshot
+//! }
+//! ```
+//!
+use std::collections::HashSet;
+
+use crate::fast::Message;
+
+/// Instruments needing recovery after decode errors.
+#[derive(Debug, Default)]
+pub struct RecoveryTracker {
+    known: HashSet<u32>,
+    stale: HashSet<u32>,
+    events: u64,
+}
+
+impl RecoveryTracker {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record instruments of the message, a snapshot recovers its instrument.
+    pub fn process(&mut self, msg: &Message) {
+        match msg {
+            Message::MDIncRefresh(msg) => {
+                self.known
+                    .extend(msg.md_entries.iter().map(|entry| entry.security_id));
+            }
+            Message::MDSecurityDefinition(msg) => {
+                self.known.insert(msg.security_id);
+            }
+            Message::MDSnapshotFullRefresh(msg) => {
+                self.known.insert(msg.security_id);
+                self.stale.remove(&msg.security_id);
+            }
+            _ => {}
+        }
+    }
+
+    /// Mark all known instruments as needing recovery after a decode error.
+    pub fn mark_all(&mut self) {
+        self.events += 1;
+        self.stale.extend(&self.known);
+    }
+
+    /// Mark the instrument as needing recovery.
+    pub fn mark(&mut self, security_id: u32) {
+        self.known.insert(security_id);
+        self.stale.insert(security_id);
+    }
+
+    #[must_use]
+    pub fn needs_recovery(&self, security_id: u32) -> bool {
+        self.stale.contains(&security_id)
+    }
+
+    /// Instruments needing recovery.
+    pub fn stale(&self) -> impl Iterator<Item = u32> + '_ {
+        self.stale.iter().copied()
+    }
+
+    /// No instrument needs recovery.
+    #[must_use]
+    pub fn is_recovered(&self) -> bool {
+        self.stale.is_empty()
+    }
+
+    /// Number of decode errors marking instruments.
+    #[must_use]
+    pub fn events(&self) -> u64 {
+        self.events
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::fast::SnapshotFullRefresh;
+    use crate::fast::fixtures::{header, inc_refresh, md_entry};
+
+    fn snapshot(security_id: u32) -> Message {
+        Message::MDSnapshotFullRefresh(SnapshotFullRefresh {
+            message_type: "W".to_string(),
+            msg_header: header(1),
+            last_msg_seq_num_processed: 1,
+            tot_num_reports: 2,
+            rpt_seq: 1,
+            security_id,
+            security_id_source: 100,
+            md_security_trading_status: None,
+            md_entries: vec![],
+        })
+    }
+
+    fn update(security_ids: &[u32]) -> Message {
+        let entries = security_ids.iter().map(|&id| md_entry(id, 1, None));
+        inc_refresh(1, entries.collect())
+    }
+
+    #[test]
+    fn test_inc_refresh_instruments() {
+        let mut recovery = RecoveryTracker::new();
+        recovery.process(&update(&[100, 200]));
+        recovery.process(&update(&[300]));
+        assert!(recovery.is_recovered());
+
+        recovery.mark_all();
+        let mut stale: Vec<_> = recovery.stale().collect();
+        stale.sort_unstable();
+        assert_eq!(stale, vec![100, 200, 300]);
+
+        // incremental updates do not recover instruments
+        recovery.process(&update(&[100]));
+        assert!(recovery.needs_recovery(100));
+        recovery.process(&snapshot(100));
+        assert!(!recovery.needs_recovery(100));
+    }
+
+    #[test]
+    fn test_recovery() {
+        let mut recovery = RecoveryTracker::new();
+        recovery.process(&snapshot(100));
+        recovery.process(&snapshot(200));
+        assert!(recovery.is_recovered());
+
+        recovery.mark_all();
+        assert!(recovery.needs_recovery(100));
+        assert!(recovery.needs_recovery(200));
+        assert_eq!(recovery.events(), 1);
+
+        recovery.process(&snapshot(100));
+        assert!(!recovery.needs_recovery(100));
+        assert_eq!(recovery.stale().collect::<Vec<_>>(), vec![200]);
+
+        recovery.process(&snapshot(200));
+        assert!(recovery.is_recovered());
+    }
+}
@@ -0,0 +1,111 @@
+//! # Security master
+//!
+//! Latest security definitions received from the Security Definition Server, keyed by `SecurityID`.
+//!
+//! # Examples
+//!
+//! ```rust,ignore
+//! use quotesdirectlib::secmaster::SecurityMaster;
+//!
+//! let mut master = SecurityMaster::new();
+//! if let Message::MDSecurityDefinition(sd) = msg {
+//!     master.insert(sd);
+//! }
+//! for sd in master.expiring_between(from, to) {
+//!     println!("{} expires at {:?}", sd.security_name, sd.expiration_time());
+//! }
+//! ```
+//!
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+
+use crate::fast::SecurityDefinition;
+
+/// Security definitions by `SecurityID`.
+#[derive(Debug, Default)]
+pub struct SecurityMaster {
+    definitions: HashMap<u32, SecurityDefinition>,
+    /// `SecurityID` by `CQGSecurityName`.
+    symbols: HashMap<String, u32>,
+}
+
+impl SecurityMaster {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add or replace the definition. Returns the replaced definition.
+    pub fn insert(&mut self, definition: SecurityDefinition) -> Option<SecurityDefinition> {
+        let security_id = definition.security_id;
+        let name = definition.cqg_security_name.clone();
+        let replaced = self.definitions.insert(security_id, definition);
+        if let Some(old) = replaced
+            .as_ref()
+            .and_then(|sd| sd.cqg_security_name.as_deref())
+        {
+            self.remove_symbol(old, security_id);
+        }
+        if let Some(name) = name {
+            self.symbols.insert(name, security_id);
+        }
+        replaced
+    }
+
+    pub fn remove(&mut self, security_id: u32) -> Option<SecurityDefinition> {
+        let removed = self.definitions.remove(&security_id)?;
+        if let Some(name) = &removed.cqg_security_name {
+            self.remove_symbol(name, security_id);
+        }
+        Some(removed)
+    }
+
+    /// `SecurityID` of the instrument with the `CQGSecurityName`.
+    #[must_use]
+    pub fn find_cqg_security_name(&self, name: &str) -> Option<u32> {
+        self.symbols.get(name).copied()
+    }
+
+    #[must_use]
+    pub fn get(&self, security_id: u32) -> Option<&SecurityDefinition> {
+        self.definitions.get(&security_id)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &SecurityDefinition> {
+        self.definitions.values()
+    }
+
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.definitions.len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.definitions.is_empty()
+    }
+
+    /// Instruments expiring in `[from, to)` ordered by expiration time.
+    /// See [`SecurityDefinition::expiration_time`].
+    #[must_use]
+    pub fn expiring_between(
+        &self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Vec<&SecurityDefinition> {
+        let mut expiring: Vec<_> = self
+            .iter()
+            .filter_map(|sd| Some((sd.expiration_time()?, sd)))
+            .filter(|(expiration, _)| from <= *expiration && *expiration < to)
+            .collect();
+        expiring.sort_by_key(|(expiration, sd)| (*expiration, sd.security_id));
+        expiring.into_iter().map(|(_, sd)| sd).collect()
+    }
+
+    /// Drop the name from the index unless it refers to another instrument.
+    fn remove_symbol(&mut self, name: &str, security_id: u32) {
+        if self.symbols.get(name) == Some(&security_id) {
+            self.symbols.remove(name);
+        }
+    }
+}
@@ -0,0 +1,341 @@
+//! # Sequence numbers tracking
+//!
+//! Both packets (preamble) and messages (`MsgSeqNum`) carry sequence numbers
+//! that are expected to increase by one. The tracker detects gaps and keeps statistics.
+//!
+//! `SequenceReset` moves the expected message sequence number to `NewSeqNo` without
+//! counting a gap. The sender restarts the FAST stream after the reset, so the decoder's
+//! dictionaries must be reset before decoding the next message.
+//!
+//! A sequence number far behind the expected one is not a duplicate but a sender restart
+//! without `SequenceReset`; the tracker re-synchronizes to it.
+//!
+use crate::fast::Message;
+
+/// Result of checking a sequence number.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum SequenceCheck {
+    /// Sequence number is the expected one.
+    InOrder,
+    /// Sequence number is ahead of the expected one, `missed` numbers skipped.
+    Gap {
+        expected: u32,
+        received: u32,
+        missed: u32,
+    },
+    /// Sequence number is behind the expected one, within the duplicate window.
+    Duplicate { expected: u32, received: u32 },
+    /// Sequence number is further behind the expected one than the duplicate window,
+    /// the sender restarted.
+    Restart { expected: u32, received: u32 },
+    /// `SequenceReset` moved the expected sequence number to `new_seq_no`.
+    Reset {
+        expected: Option<u32>,
+        new_seq_no: u32,
+    },
+}
+
+/// Sequence numbers statistics.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub struct SequenceStats {
+    /// Total sequence numbers checked.
+    pub received: u64,
+    /// Number of gaps detected.
+    pub gaps: u64,
+    /// Total sequence numbers missed in gaps.
+    pub missed: u64,
+    /// Number of sequence numbers received behind the expected one.
+    pub duplicates: u64,
+    /// Number of `SequenceReset` messages honoured.
+    pub resets: u64,
+    /// Number of sender restarts detected.
+    pub restarts: u64,
+}
+
+/// Default number of sequence numbers behind the expected one that are taken as duplicates.
+pub const DEFAULT_DUPLICATE_WINDOW: u32 = 1000;
+
+/// Sequence numbers tracker.
+///
+/// On a gap or a restart the tracker re-synchronizes to the received sequence number,
+/// duplicates leave the expected one unchanged.
+///
+/// # Examples
+///
+/// ```
+/// use quotesdirectlib::sequence::{SequenceCheck, SequenceTracker};
+///
+/// let mut seq = SequenceTracker::new();
+/// assert_eq!(seq.check(1), SequenceCheck::InOrder);
+/// assert_eq!(seq.check(4), SequenceCheck::Gap { expected: 2, received: 4, missed: 2 });
+/// assert_eq!(seq.stats().missed, 2);
+/// ```
+#[derive(Debug, Clone)]
+pub struct SequenceTracker {
+    expected: Option<u32>,
+    duplicate_window: u32,
+    stats: SequenceStats,
+}
+
+impl Default for SequenceTracker {
+    fn default() -> Self {
+        Self {
+            expected: None,
+            duplicate_window: DEFAULT_DUPLICATE_WINDOW,
+            stats: SequenceStats::default(),
+        }
+    }
+}
+
+impl SequenceTracker {
+    /// Create a tracker that accepts any sequence number first.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a tracker that expects `seq_num` first.
+    #[must_use]
+    pub fn starting_at(seq_num: u32) -> Self {
+        Self {
+            expected: Some(seq_num),
+            ..Self::default()
+        }
+    }
+
+    /// Take sequence numbers up to `window` behind the expected one as duplicates,
+    /// and any further behind as a sender restart.
+    #[must_use]
+    pub fn with_duplicate_window(mut self, window: u32) -> Self {
+        self.duplicate_window = window;
+        self
+    }
+
+    /// Check received sequence number and update the expected one.
+    pub fn check(&mut self, seq_num: u32) -> SequenceCheck {
+        self.stats.received += 1;
+        let result = match self.expected {
+            Some(expected) if seq_num > expected => {
+                let missed = seq_num - expected;
+                self.stats.gaps += 1;
+                self.stats.missed += u64::from(missed);
+                SequenceCheck::Gap {
+                    expected,
+                    received: seq_num,
+                    missed,
+                }
+            }
+            Some(expected) if expected - seq_num > self.duplicate_window => {
+                self.stats.restarts += 1;
+                SequenceCheck::Restart {
+                    expected,
+                    received: seq_num,
+                }
+            }
+            Some(expected) if seq_num < expected => {
+                self.stats.duplicates += 1;
+                return SequenceCheck::Duplicate {
+                    expected,
+                    received: seq_num,
+                };
+            }
+            _ => SequenceCheck::InOrder,
+        };
+        self.expected = Some(seq_num.wrapping_add(1));
+        result
+    }
+
+    /// Check a message's `MsgSeqNum`, honouring `SequenceReset`.
+    pub fn check_message(&mut self, msg: &Message) -> SequenceCheck {
+        match msg {
+            Message::SequenceReset(m) => {
+                self.stats.received += 1;
+                self.reset(m.new_seq_no)
+            }
+            _ => self.check(msg.msg_header().msg_seq_num),
+        }
+    }
+
+    /// Expect `new_seq_no` next without counting a gap.
+    pub fn reset(&mut self, new_seq_no: u32) -> SequenceCheck {
+        self.stats.resets += 1;
+        SequenceCheck::Reset {
+            expected: self.expected.replace(new_seq_no),
+            new_seq_no,
+        }
+    }
+
+    /// Accept any sequence number next without counting a gap,
+    /// e.g. after messages were lost locally.
+    pub fn resync(&mut self) {
+        self.expected = None;
+    }
+
+    /// Sequence number expected next, if known.
+    #[must_use]
+    pub fn expected(&self) -> Option<u32> {
+        self.expected
+    }
+
+    #[must_use]
+    pub fn stats(&self) -> &SequenceStats {
+        &self.stats
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::fast::fixtures::{header, heartbeat};
+    use crate::fast::{SequenceReset, TEMPLATES_XML};
+    use fastlib::{Decoder, Encoder};
+
+    #[test]
+    fn test_in_order() {
+        let mut seq = SequenceTracker::starting_at(1);
+        assert_eq!(seq.check(1), SequenceCheck::InOrder);
+        assert_eq!(seq.check(2), SequenceCheck::InOrder);
+        assert_eq!(seq.expected(), Some(3));
+        assert_eq!(
+            *seq.stats(),
+            SequenceStats {
+                received: 2,
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_gap_and_duplicate() {
+        let mut seq = SequenceTracker::new();
+        assert_eq!(seq.check(10), SequenceCheck::InOrder);
+        assert_eq!(
+            seq.check(13),
+            SequenceCheck::Gap {
+                expected: 11,
+                received: 13,
+                missed: 2
+            }
+        );
+        assert_eq!(
+            seq.check(12),
+            SequenceCheck::Duplicate {
+                expected: 14,
+                received: 12
+            }
+        );
+        assert_eq!(
+            seq.check(13),
+            SequenceCheck::Duplicate {
+                expected: 14,
+                received: 13
+            }
+        );
+        assert_eq!(seq.check(14), SequenceCheck::InOrder);
+        assert_eq!(seq.expected(), Some(15));
+        assert_eq!(
+            *seq.stats(),
+            SequenceStats {
+                received: 5,
+                gaps: 1,
+                missed: 2,
+                duplicates: 2,
+                resets: 0,
+                restarts: 0,
+            }
+        );
+    }
+
+    #[test]
+    fn test_restart() {
+        let mut seq = SequenceTracker::starting_at(5000).with_duplicate_window(100);
+        assert_eq!(
+            seq.check(4900),
+            SequenceCheck::Duplicate {
+                expected: 5000,
+                received: 4900
+            }
+        );
+        // the sender restarted without SequenceReset
+        assert_eq!(
+            seq.check(1),
+            SequenceCheck::Restart {
+                expected: 5000,
+                received: 1
+            }
+        );
+        assert_eq!(seq.check(2), SequenceCheck::InOrder);
+        assert_eq!(seq.check(3), SequenceCheck::InOrder);
+        assert_eq!(seq.expected(), Some(4));
+        assert_eq!(
+            *seq.stats(),
+            SequenceStats {
+                received: 4,
+                duplicates: 1,
+                restarts: 1,
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_reset_in_packet() {
+        let messages = [
+            heartbeat(5),
+            heartbeat(6),
+            Message::SequenceReset(SequenceReset {
+                message_type: "4".to_string(),
+                msg_header: header(7),
+                new_seq_no: 100,
+            }),
+            heartbeat(100),
+            heartbeat(101),
+        ];
+
+        // encode all messages into one packet, restarting the stream after the reset
+        let mut encoder = Encoder::new_from_xml(TEMPLATES_XML).unwrap();
+        let mut packet = Vec::new();
+        for msg in &messages {
+            packet.extend(fastlib::to_vec(&mut encoder, msg).unwrap());
+            if matches!(msg, Message::SequenceReset(_)) {
+                encoder.reset();
+            }
+        }
+
+        let mut decoder = Decoder::new_from_xml(TEMPLATES_XML).unwrap();
+        let mut seq = SequenceTracker::starting_at(5);
+        let mut offset = 0;
+        let mut checks = Vec::new();
+        while offset < packet.len() {
+            let (msg, size): (Message, u64) =
+                fastlib::from_buffer(&mut decoder, &packet[offset..]).unwrap();
+            offset += usize::try_from(size).unwrap();
+            let check = seq.check_message(&msg);
+            if let SequenceCheck::Reset { .. } = check {
+                decoder.reset();
+            }
+            checks.push(check);
+        }
+        assert_eq!(
+            checks,
+            vec![
+                SequenceCheck::InOrder,
+                SequenceCheck::InOrder,
+                SequenceCheck::Reset {
+                    expected: Some(7),
+                    new_seq_no: 100
+                },
+                SequenceCheck::InOrder,
+                SequenceCheck::InOrder,
+            ]
+        );
+        assert_eq!(
+            *seq.stats(),
+            SequenceStats {
+                received: 5,
+                resets: 1,
+                ..Default::default()
+            }
+        );
+    }
+}
@@ -0,0 +1,483 @@
+//! # Spreads and strategies
+//!
+//! Resolves legs of spread and strategy definitions (`SecurityDefinition.Legs`)
+//! to their own definitions, checks leg ratios and computes implied prices:
+//! of the spread from its legs' books, and of a leg from the spread's and other legs' books.
+//!
+//! The spread price is the sum of leg prices multiplied by their ratios,
+//! bought legs (`LegSide` = 1) taken with plus and sold legs (`LegSide` = 2) with minus,
+//! e.g. a calendar spread is priced as front month minus back month.
+//!
+//! # Examples
+//!
+//! ```rust,ignore
+//! use quotesdirectlib::spreads::{check_legs, implied_spread};
+//!
+//! for issue in check_legs(&spread, &master) {
+//!     warn!("{}: {issue:?}", spread.security_name);
+//! }
+//! if let Some(quote) = implied_spread(&spread, &books) {
+//!     println!("implied {:?} / {:?}", quote.bid, quote.offer);
+//! }
+//! ```
+//!
+use fastlib::Decimal;
+
+use crate::book::{Level, OrderBook, OrderBooks};
+use crate::fast::{Leg, SecurityDefinition};
+use crate::secmaster::SecurityMaster;
+
+/// `LegSide` (624) values.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum LegSide {
+    Buy,
+    Sell,
+    Other(u32),
+}
+
+impl LegSide {
+    #[must_use]
+    pub fn from_fix(value: u32) -> Self {
+        match value {
+            1 => LegSide::Buy,
+            2 => LegSide::Sell,
+            _ => LegSide::Other(value),
+        }
+    }
+
+    fn sign(self) -> Option<i128> {
+        match self {
+            LegSide::Buy => Some(1),
+            LegSide::Sell => Some(-1),
+            LegSide::Other(_) => None,
+        }
+    }
+}
+
+/// Leg with its own definition from the security master.
+#[derive(Debug, Clone, Copy)]
+pub struct ResolvedLeg<'a> {
+    pub leg: &'a Leg,
+    pub side: LegSide,
+    /// `None` if the leg's definition is not in the security master.
+    pub definition: Option<&'a SecurityDefinition>,
+}
+
+/// Inconsistency of a spread definition.
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+pub enum LegIssue {
+    /// The definition has no legs.
+    NoLegs,
+    /// `LegSide` is neither buy nor sell.
+    UnknownSide { leg_security_id: u32 },
+    /// `LegRatioQty` is zero or negative.
+    NonPositiveRatio { leg_security_id: u32 },
+    /// `LegRatioQty` is not a whole number.
+    FractionalRatio { leg_security_id: u32 },
+    /// Ratios are not reduced, e.g. 2:2 instead of 1:1.
+    CommonFactor(u32),
+    /// Two-leg calendar spread legs have different ratios or the same side.
+    UnbalancedCalendar,
+    /// The leg's definition is not in the security master.
+    UnresolvedLeg { leg_security_id: u32 },
+    /// `LegMaturityMonthYear` differs from the leg's definition.
+    MaturityMismatch { leg_security_id: u32 },
+}
+
+/// Implied top of book.
+#[derive(Debug, PartialEq, Clone)]
+pub struct ImpliedQuote {
+    pub bid: Option<Level>,
+    pub offer: Option<Level>,
+}
+
+impl SecurityMaster {
+    /// Resolve legs of the spread to their definitions.
+    #[must_use]
+    pub fn resolve_legs<'a>(&'a self, spread: &'a SecurityDefinition) -> Vec<ResolvedLeg<'a>> {
+        spread
+            .legs
+            .iter()
+            .flatten()
+            .map(|leg| ResolvedLeg {
+                leg,
+                side: LegSide::from_fix(leg.leg_side),
+                definition: self.get(leg.leg_security_id),
+            })
+            .collect()
+    }
+}
+
+/// Check leg ratios and sides of the spread and its legs' definitions in the security master.
+#[must_use]
+pub fn check_legs(spread: &SecurityDefinition, master: &SecurityMaster) -> Vec<LegIssue> {
+    let legs = master.resolve_legs(spread);
+    if legs.is_empty() {
+        return vec![LegIssue::NoLegs];
+    }
+    let mut issues = Vec::new();
+    let mut ratios = Vec::new();
+    for resolved in &legs {
+        let leg_security_id = resolved.leg.leg_security_id;
+        if resolved.side.sign().is_none() {
+            issues.push(LegIssue::UnknownSide { leg_security_id });
+        }
+        match ratio_qty(&resolved.leg.leg_ratio_qty) {
+            Ok(ratio) => ratios.push(ratio),
+            Err(issue) => issues.push(issue(leg_security_id)),
+        }
+        match resolved.definition {
+            None => issues.push(LegIssue::UnresolvedLeg { leg_security_id }),
+            Some(definition) => {
+                if definition
+                    .maturity_month_year
+                    .is_some_and(|m| m != resolved.leg.leg_maturity_month_year)
+                {
+                    issues.push(LegIssue::MaturityMismatch { leg_security_id });
+                }
+            }
+        }
+    }
+    if ratios.len() == legs.len() {
+        let factor = ratios.iter().copied().reduce(gcd).unwrap_or(1);
+        if factor > 1 {
+            issues.push(LegIssue::CommonFactor(factor));
+        }
+    }
+    if let [front, back] = legs.as_slice()
+        && front.leg.leg_security_group == back.leg.leg_security_group
+        && front.leg.leg_maturity_month_year != back.leg.leg_maturity_month_year
+        && (front.leg.leg_ratio_qty != back.leg.leg_ratio_qty || front.side == back.side)
+    {
+        issues.push(LegIssue::UnbalancedCalendar);
+    }
+    issues
+}
+
+/// Implied top of book of the spread from its legs' books.
+/// Returns `None` if a leg's book is not tracked or legs are inconsistent.
+#[must_use]
+pub fn implied_spread(spread: &SecurityDefinition, books: &OrderBooks) -> Option<ImpliedQuote> {
+    let legs = spread_legs(spread, books)?;
+    let quote = |bid: bool| {
+        let terms = legs.iter().map(|leg| {
+            // buying the spread buys the bought legs and sells the sold ones
+            let level = best(leg.book, (leg.sign > 0) == bid)?;
+            Some((leg.sign, leg.ratio, level))
+        });
+        implied_level(terms, 1)
+    };
+    Some(ImpliedQuote {
+        bid: quote(true),
+        offer: quote(false),
+    })
+}
+
+/// Implied top of book of a leg from the spread's and other legs' books.
+/// Returns `None` if the instrument is not a leg of the spread, a book is not tracked
+/// or legs are inconsistent.
+#[must_use]
+pub fn implied_leg(
+    spread: &SecurityDefinition,
+    leg_security_id: u32,
+    books: &OrderBooks,
+) -> Option<ImpliedQuote> {
+    let spread_book = books.get(spread.security_id)?;
+    let legs = spread_legs(spread, books)?;
+    let target = legs.iter().find(|leg| leg.security_id == leg_security_id)?;
+    let quote = |bid: bool| {
+        // leg price = (spread price - other legs' terms) / ratio, negated for sold legs
+        let spread_level = best(spread_book, (target.sign > 0) == bid)?;
+        let terms = std::iter::once(Some((target.sign, 1, spread_level))).chain(
+            legs.iter()
+                .filter(|leg| leg.security_id != leg_security_id)
+                .map(|leg| {
+                    let level = best(leg.book, (leg.sign * target.sign > 0) != bid)?;
+                    Some((-leg.sign * target.sign, leg.ratio, level))
+                }),
+        );
+        implied_level(terms, target.ratio)
+    };
+    Some(ImpliedQuote {
+        bid: quote(true),
+        offer: quote(false),
+    })
+}
+
+struct BookLeg<'a> {
+    security_id: u32,
+    sign: i128,
+    ratio: u32,
+    book: &'a OrderBook,
+}
+
+fn spread_legs<'a>(spread: &SecurityDefinition, books: &'a OrderBooks) -> Option<Vec<BookLeg<'a>>> {
+    let legs = spread.legs.as_ref().filter(|legs| !legs.is_empty())?;
+    legs.iter()
+        .map(|leg| {
+            Some(BookLeg {
+                security_id: leg.leg_security_id,
+                sign: LegSide::from_fix(leg.leg_side).sign()?,
+                ratio: ratio_qty(&leg.leg_ratio_qty).ok()?,
+                book: books.get(leg.leg_security_id)?,
+            })
+        })
+        .collect()
+}
+
+fn best(book: &OrderBook, bid: bool) -> Option<&Level> {
+    if bid {
+        book.best_bid()
+    } else {
+        book.best_offer()
+    }
+}
+
+/// Sum `sign * ratio * price` of the terms and divide by `divisor`.
+/// The size is limited by the smallest level in units of its ratio.
+fn implied_level<'a>(
+    terms: impl Iterator<Item = Option<(i128, u32, &'a Level)>>,
+    divisor: u32,
+) -> Option<Level> {
+    let mut price = Num::ZERO;
+    let mut size = i32::MAX;
+    for term in terms {
+        let (sign, ratio, level) = term?;
+        let value = Num::from(&level.price).mul(sign * i128::from(ratio))?;
+        price = price.add(value)?;
+        size = size.min(level.size / i32::try_from(ratio).ok()?);
+    }
+    Some(Level {
+        price: price.div(divisor)?.to_decimal()?,
+        size: size.checked_mul(i32::try_from(divisor).ok()?)?,
+    })
+}
+
+/// Whole positive leg ratio.
+fn ratio_qty(ratio: &Decimal) -> Result<u32, fn(u32) -> LegIssue> {
+    if ratio.mantissa <= 0 {
+        return Err(|leg_security_id| LegIssue::NonPositiveRatio { leg_security_id });
+    }
+    let fractional = |leg_security_id| LegIssue::FractionalRatio { leg_security_id };
+    let mut mantissa = ratio.mantissa;
+    let mut exponent = ratio.exponent;
+    while exponent < 0 {
+        if mantissa % 10 != 0 {
+            return Err(fractional);
+        }
+        mantissa /= 10;
+        exponent += 1;
+    }
+    10i64
+        .checked_pow(exponent.unsigned_abs())
+        .and_then(|scale| mantissa.checked_mul(scale))
+        .and_then(|value| u32::try_from(value).ok())
+        .ok_or(fractional)
+}
+
+fn gcd(a: u32, b: u32) -> u32 {
+    if b == 0 { a } else { gcd(b, a % b) }
+}
+
+/// Exact decimal arithmetic for implied prices.
+#[derive(Debug, Clone, Copy)]
+struct Num {
+    mantissa: i128,
+    exponent: i32,
+}
+
+impl Num {
+    const ZERO: Num = Num {
+        mantissa: 0,
+        exponent: 0,
+    };
+
+    fn from(value: &Decimal) -> Self {
+        Self {
+            mantissa: i128::from(value.mantissa),
+            exponent: value.exponent,
+        }
+    }
+
+    fn rescale(self, exponent: i32) -> Option<i128> {
+        let scale = 10i128.checked_pow((self.exponent - exponent).try_into().ok()?)?;
+        self.mantissa.checked_mul(scale)
+    }
+
+    fn add(self, other: Num) -> Option<Num> {
+        let exponent = self.exponent.min(other.exponent);
+        Some(Num {
+            mantissa: self
+                .rescale(exponent)?
+                .checked_add(other.rescale(exponent)?)?,
+            exponent,
+        })
+    }
+
+    fn mul(self, factor: i128) -> Option<Num> {
+        Some(Num {
+            mantissa: self.mantissa.checked_mul(factor)?,
+            exponent: self.exponent,
+        })
+    }
+
+    /// Divide adding decimal places until exact; `None` if the result does not terminate soon.
+    fn div(mut self, divisor: u32) -> Option<Num> {
+        let divisor = i128::from(divisor);
+        for _ in 0..8 {
+            if self.mantissa % divisor == 0 {
+                return Some(Num {
+                    mantissa: self.mantissa / divisor,
+                    exponent: self.exponent,
+                });
+            }
+            self = Num {
+                mantissa: self.mantissa.checked_mul(10)?,
+                exponent: self.exponent - 1,
+            };
+        }
+        None
+    }
+
+    fn to_decimal(self) -> Option<Decimal> {
+        Some(Decimal::new(
+            self.exponent,
+            i64::try_from(self.mantissa).ok()?,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::fast::fixtures;
+    use crate::market::{BookUpdate, Side, UpdateAction};
+
+    fn leg(security_id: u32, side: u32, ratio: i64, maturity: u64) -> Leg {
+        Leg {
+            leg_symbol: "EP".to_string(),
+            leg_security_desc: String::new(),
+            leg_ratio_qty: Decimal::new(0, ratio),
+            leg_security_id: security_id,
+            leg_security_id_source: 8,
+            leg_side: side,
+            leg_security_group: "EP".to_string(),
+            leg_cfi_code: "FFIXSX".to_string(),
+            leg_currency: "USD".to_string(),
+            leg_maturity_month_year: maturity,
+            leg_strike_price: Decimal::default(),
+        }
+    }
+
+    fn definition(security_id: u32, maturity: u64, legs: Option<Vec<Leg>>) -> SecurityDefinition {
+        SecurityDefinition {
+            security_group: Some("EP".to_string()),
+            cfi_code: "FMIXSX".to_string(),
+            maturity_month_year: Some(maturity),
+            legs,
+            ..fixtures::definition(security_id)
+        }
+    }
+
+    fn quote(books: &mut OrderBooks, security_id: u32, bid: i64, offer: i64) {
+        let book = books.track(security_id);
+        for (side, price) in [(Side::Bid, bid), (Side::Offer, offer)] {
+            book.apply(&BookUpdate {
+                security_id,
+                rpt_seq: None,
+                action: UpdateAction::New,
+                side,
+                implied: false,
+                level: Some(1),
+                price: Some(Decimal::new(-2, price)),
+                size: Some(10),
+                time: None,
+                sending_time: None,
+            });
+        }
+    }
+
+    fn price(level: Option<Level>) -> i64 {
+        let price = level.unwrap().price;
+        assert_eq!(price.exponent, -2);
+        price.mantissa
+    }
+
+    #[test]
+    fn test_check_legs() {
+        let mut master = SecurityMaster::new();
+        master.insert(definition(1, 202509, None));
+        master.insert(definition(2, 202512, None));
+
+        let calendar = definition(
+            3,
+            202509,
+            Some(vec![leg(1, 1, 1, 202509), leg(2, 2, 1, 202512)]),
+        );
+        assert!(check_legs(&calendar, &master).is_empty());
+        assert_eq!(
+            master.resolve_legs(&calendar)[1]
+                .definition
+                .unwrap()
+                .security_id,
+            2
+        );
+
+        let broken = definition(
+            4,
+            202509,
+            Some(vec![
+                leg(1, 1, 2, 202509),
+                leg(2, 1, 2, 202603),
+                leg(5, 3, 0, 202509),
+            ]),
+        );
+        assert_eq!(
+            check_legs(&broken, &master),
+            vec![
+                LegIssue::MaturityMismatch { leg_security_id: 2 },
+                LegIssue::UnknownSide { leg_security_id: 5 },
+                LegIssue::NonPositiveRatio { leg_security_id: 5 },
+                LegIssue::UnresolvedLeg { leg_security_id: 5 },
+            ]
+        );
+        let unbalanced = definition(
+            5,
+            202509,
+            Some(vec![leg(1, 1, 2, 202509), leg(2, 1, 2, 202512)]),
+        );
+        assert_eq!(
+            check_legs(&unbalanced, &master),
+            vec![LegIssue::CommonFactor(2), LegIssue::UnbalancedCalendar]
+        );
+        assert_eq!(
+            check_legs(&definition(1, 202509, None), &master),
+            vec![LegIssue::NoLegs]
+        );
+    }
+
+    #[test]
+    fn test_implied_prices() {
+        let calendar = definition(
+            3,
+            202509,
+            Some(vec![leg(1, 1, 1, 202509), leg(2, 2, 1, 202512)]),
+        );
+        let mut books = OrderBooks::new();
+        quote(&mut books, 1, 600000, 600025);
+        quote(&mut books, 2, 605000, 605050);
+
+        let spread = implied_spread(&calendar, &books).unwrap();
+        assert_eq!(price(spread.bid), 600000 - 605050);
+        assert_eq!(price(spread.offer), 600025 - 605000);
+
+        quote(&mut books, 3, -5025, -4975);
+        let front = implied_leg(&calendar, 1, &books).unwrap();
+        assert_eq!(price(front.bid), -5025 + 605000);
+        assert_eq!(price(front.offer), -4975 + 605050);
+        let back = implied_leg(&calendar, 2, &books).unwrap();
+        assert_eq!(price(back.bid), 600000 + 4975);
+        assert_eq!(price(back.offer), 600025 + 5025);
+        assert!(implied_leg(&calendar, 7, &books).is_none());
+    }
+}
@@ -0,0 +1,356 @@
+//! # Session statistics
+//!
+//! Maintains per-instrument session statistics published as statistical `MDEntryType` values
+//! of `MDIncRefresh` and `MDSnapshotFullRefresh` messages:
+//! opening price, session high/low, last trade price, settlement, cleared volume and open interest.
+//!
+//! Statistics are kept per trade date. Data for a newer trade date starts a new session,
+//! the previous session is retained so a settlement published after the rollover is not lost.
+//!
+//! # Examples
+//!
+//! ```rust,ignore
+//! use quotesdirectlib::stats::StatisticsTracker;
+//!
+//! let mut stats = StatisticsTracker::new();
+//! stats.process(&msg);
+//! if let Some(session) = stats.get(security_id) {
+//!     println!("{:?}", session.settlement);
+//! }
+//! ```
+//!
+use chrono::{DateTime, NaiveDate, Utc};
+use fastlib::Decimal;
+use std::collections::HashMap;
+
+use crate::fast::Message;
+use crate::market::{EntryType, MarketEvent, Statistic, StatisticKind, TradeEvent, normalize};
+
+/// Settlement price type.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum SettlementType {
+    Preliminary,
+    Final,
+    /// Settlement of a snapshot, which does not carry `MDQuoteType`.
+    Unknown,
+}
+
+impl SettlementType {
+    /// Settlement type from `MDQuoteType` (1070) of an incremental entry:
+    /// indicative (0) settlement is preliminary.
+    #[must_use]
+    pub fn from_quote_type(quote_type: Option<u32>) -> Self {
+        match quote_type {
+            Some(0) => SettlementType::Preliminary,
+            _ => SettlementType::Final,
+        }
+    }
+
+    /// Settlement type of a statistic, unknown for snapshot entries.
+    #[must_use]
+    pub fn from_statistic(stat: &Statistic) -> Self {
+        match stat.rpt_seq {
+            Some(_) => Self::from_quote_type(stat.quote_type),
+            None => SettlementType::Unknown,
+        }
+    }
+}
+
+/// Settlement price.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Settlement {
+    pub price: Decimal,
+    pub settlement_type: SettlementType,
+}
+
+/// Statistics of one instrument for one trade date.
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct SessionStatistics {
+    /// `None` until a message with the trade date is received.
+    pub trade_date: Option<NaiveDate>,
+    pub open: Option<Decimal>,
+    pub high: Option<Decimal>,
+    pub low: Option<Decimal>,
+    pub last: Option<Decimal>,
+    pub settlement: Option<Settlement>,
+    pub cleared_volume: Option<i32>,
+    pub open_interest: Option<i32>,
+    /// Sending time of the last message that updated the statistics.
+    pub updated: Option<DateTime<Utc>>,
+}
+
+impl SessionStatistics {
+    fn new(trade_date: Option<NaiveDate>) -> Self {
+        Self {
+            trade_date,
+            ..Default::default()
+        }
+    }
+
+    fn apply_statistic(&mut self, stat: &Statistic) {
+        match stat.kind {
+            StatisticKind::OpeningPrice => self.open.clone_from(&stat.price),
+            StatisticKind::SessionHigh => self.high.clone_from(&stat.price),
+            StatisticKind::SessionLow => self.low.clone_from(&stat.price),
+            StatisticKind::SettlementPrice => {
+                let settlement_type = SettlementType::from_statistic(stat);
+                let is_final = self
+                    .settlement
+                    .as_ref()
+                    .is_some_and(|s| s.settlement_type == SettlementType::Final);
+                // only a final settlement replaces the final one
+                if let Some(price) = stat.price.clone()
+                    && (!is_final || settlement_type == SettlementType::Final)
+                {
+                    self.settlement = Some(Settlement {
+                        price,
+                        settlement_type,
+                    });
+                }
+            }
+            StatisticKind::ClearedVolume => self.cleared_volume = stat.size,
+            StatisticKind::OpenInterest => self.open_interest = stat.size,
+            StatisticKind::Other(_) => return,
+        }
+        self.updated = stat.sending_time.or(self.updated);
+    }
+}
+
+#[derive(Debug, Default)]
+struct InstrumentStatistics {
+    current: SessionStatistics,
+    previous: Option<SessionStatistics>,
+}
+
+impl InstrumentStatistics {
+    /// Session for the trade date, rolling over to a new session if the date is newer.
+    /// Returns `None` for dates older than the retained sessions.
+    fn session(&mut self, trade_date: Option<NaiveDate>) -> Option<&mut SessionStatistics> {
+        let Some(date) = trade_date else {
+            return Some(&mut self.current);
+        };
+        match self.current.trade_date {
+            None => self.current.trade_date = Some(date),
+            Some(current) if date > current => {
+                let previous =
+                    std::mem::replace(&mut self.current, SessionStatistics::new(trade_date));
+                self.previous = Some(previous);
+            }
+            Some(current) if date < current => {
+                return self
+                    .previous
+                    .as_mut()
+                    .filter(|previous| previous.trade_date == trade_date);
+            }
+            Some(_) => {}
+        }
+        Some(&mut self.current)
+    }
+}
+
+/// Tracks session statistics of instruments.
+#[derive(Debug, Default)]
+pub struct StatisticsTracker {
+    instruments: HashMap<u32, InstrumentStatistics>,
+}
+
+impl StatisticsTracker {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Update statistics from a message.
+    pub fn process(&mut self, msg: &Message) {
+        for event in normalize(msg) {
+            self.process_event(&event);
+        }
+        // last trade is part of the snapshot's state, it is not normalized into a trade event
+        if let Message::MDSnapshotFullRefresh(m) = msg {
+            let last = m
+                .md_entries
+                .iter()
+                .filter(|e| EntryType::from_fix(&e.md_entry_type) == EntryType::Trade)
+                .find_map(|e| e.md_entry_px.clone());
+            if let Some(last) = last {
+                self.instruments
+                    .entry(m.security_id)
+                    .or_default()
+                    .current
+                    .last = Some(last);
+            }
+        }
+    }
+
+    /// Update statistics from a market event.
+    pub fn process_event(&mut self, event: &MarketEvent) {
+        match event {
+            MarketEvent::Statistic(stat) => self.process_statistic(stat),
+            MarketEvent::Trade(trade) => self.process_trade(trade),
+            _ => {}
+        }
+    }
+
+    fn process_statistic(&mut self, stat: &Statistic) {
+        let instrument = self.instruments.entry(stat.security_id).or_default();
+        if let Some(session) = instrument.session(stat.trade_date) {
+            session.apply_statistic(stat);
+        }
+    }
+
+    fn process_trade(&mut self, trade: &TradeEvent) {
+        let instrument = self.instruments.entry(trade.security_id).or_default();
+        if let Some(session) = instrument.session(trade.trade_date)
+            && trade.price.is_some()
+        {
+            session.last.clone_from(&trade.price);
+            session.updated = trade.sending_time.or(session.updated);
+        }
+    }
+
+    /// Statistics of the instrument's current session.
+    #[must_use]
+    pub fn get(&self, security_id: u32) -> Option<&SessionStatistics> {
+        self.instruments.get(&security_id).map(|i| &i.current)
+    }
+
+    /// Statistics of the instrument for the trade date, if still retained.
+    #[must_use]
+    pub fn get_for_date(
+        &self,
+        security_id: u32,
+        trade_date: NaiveDate,
+    ) -> Option<&SessionStatistics> {
+        let instrument = self.instruments.get(&security_id)?;
+        std::iter::once(&instrument.current)
+            .chain(instrument.previous.as_ref())
+            .find(|s| s.trade_date == Some(trade_date))
+    }
+
+    /// Current sessions of all instruments.
+    pub fn iter(&self) -> impl Iterator<Item = (u32, &SessionStatistics)> {
+        self.instruments.iter().map(|(id, i)| (*id, &i.current))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::fast::fixtures::{header, inc_refresh, md_entry};
+    use crate::fast::{MDEntrySnapshot, SnapshotFullRefresh};
+
+    fn stat(kind: StatisticKind, trade_date: Option<NaiveDate>, price: i64) -> MarketEvent {
+        MarketEvent::Statistic(Statistic {
+            security_id: 1,
+            rpt_seq: Some(1),
+            kind,
+            trade_date,
+            price: Some(Decimal::new(-2, price)),
+            size: Some(100),
+            quote_type: None,
+            time: None,
+            sending_time: None,
+        })
+    }
+
+    #[test]
+    fn test_rollover() {
+        let day1 = NaiveDate::from_ymd_opt(2025, 6, 19);
+        let day2 = NaiveDate::from_ymd_opt(2025, 6, 20);
+        let mut stats = StatisticsTracker::new();
+        stats.process_event(&stat(StatisticKind::OpeningPrice, day1, 100));
+        stats.process_event(&stat(StatisticKind::OpenInterest, day1, 0));
+        stats.process_event(&stat(StatisticKind::SessionHigh, day2, 120));
+
+        let current = stats.get(1).unwrap();
+        assert_eq!(current.trade_date, day2);
+        assert_eq!(current.high, Some(Decimal::new(-2, 120)));
+        assert_eq!(current.open, None);
+        assert_eq!(current.open_interest, None);
+
+        // late settlement of the previous session
+        stats.process_event(&stat(StatisticKind::SettlementPrice, day1, 110));
+        let previous = stats.get_for_date(1, day1.unwrap()).unwrap();
+        assert_eq!(previous.open, Some(Decimal::new(-2, 100)));
+        assert_eq!(previous.open_interest, Some(100));
+        assert_eq!(
+            previous.settlement,
+            Some(Settlement {
+                price: Decimal::new(-2, 110),
+                settlement_type: SettlementType::Final
+            })
+        );
+        assert_eq!(stats.get(1).unwrap().settlement, None);
+    }
+
+    #[test]
+    fn test_settlement() {
+        let mut stats = StatisticsTracker::new();
+        let MarketEvent::Statistic(mut preliminary) =
+            stat(StatisticKind::SettlementPrice, None, 100)
+        else {
+            unreachable!()
+        };
+        preliminary.quote_type = Some(0);
+        stats.process_event(&MarketEvent::Statistic(preliminary.clone()));
+        assert_eq!(
+            stats
+                .get(1)
+                .unwrap()
+                .settlement
+                .as_ref()
+                .unwrap()
+                .settlement_type,
+            SettlementType::Preliminary
+        );
+
+        stats.process_event(&stat(StatisticKind::SettlementPrice, None, 101));
+        stats.process_event(&MarketEvent::Statistic(preliminary));
+        let settlement = stats.get(1).unwrap().settlement.clone().unwrap();
+        assert_eq!(settlement.price, Decimal::new(-2, 101));
+        assert_eq!(settlement.settlement_type, SettlementType::Final);
+    }
+
+    #[test]
+    fn test_snapshot_settlement() {
+        let settlement = |price| MDEntrySnapshot {
+            md_entry_type: "6".to_string(),
+            md_entry_px: Some(Decimal::new(-2, price)),
+            md_entry_size: None,
+            quote_condition: None,
+            md_price_level: None,
+            md_workup_state: None,
+        };
+        let snapshot = Message::MDSnapshotFullRefresh(SnapshotFullRefresh {
+            message_type: "W".to_string(),
+            msg_header: header(1),
+            last_msg_seq_num_processed: 1,
+            tot_num_reports: 1,
+            rpt_seq: 1,
+            security_id: 1,
+            security_id_source: 100,
+            md_security_trading_status: None,
+            md_entries: vec![settlement(100)],
+        });
+        let mut stats = StatisticsTracker::new();
+        stats.process(&snapshot);
+        let session = stats.get(1).unwrap();
+        assert_eq!(
+            session.settlement.as_ref().unwrap().settlement_type,
+            SettlementType::Unknown
+        );
+
+        // preliminary settlement of the incremental feed replaces the snapshot's
+        let mut preliminary = md_entry(1, 2, Some(Decimal::new(-2, 101)));
+        preliminary.md_entry_type = "6".to_string();
+        preliminary.md_quote_type = Some(0);
+        stats.process(&inc_refresh(2, vec![preliminary]));
+        assert_eq!(
+            stats.get(1).unwrap().settlement,
+            Some(Settlement {
+                price: Decimal::new(-2, 101),
+                settlement_type: SettlementType::Preliminary
+            })
+        );
+    }
+}
@@ -0,0 +1,210 @@
+//! # Trading status
+//!
+//! Tracks trading status of instruments from `MDSecurityStatus` messages
+//! and `MDSecurityTradingStatus` of snapshots, and reports status transitions.
+//!
+//! `MDSecurityStatus` with only `Symbol` set applies to the whole group of instruments
+//! sharing the symbol. Instruments are assigned to groups from their security definitions
+//! (or [`StatusRegistry::register`]) and inherit the group's status when they have none of their own.
+//!
+//! # Examples
+//!
+//! ```rust,ignore
+//! use quotesdirectlib::status::StatusRegistry;
+//!
+//! let mut registry = StatusRegistry::new();
+//! for transition in registry.process(&msg) {
+//!     book.apply_status(&transition);
+//! }
+//! ```
+//!
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+
+use crate::fast::{Message, SecurityDefinition};
+use crate::market::{MarketEvent, StatusChange, TradingStatus, normalize};
+
+/// Change of an instrument's trading status.
+#[derive(Debug, PartialEq, Clone)]
+pub struct StatusTransition {
+    pub security_id: u32,
+    /// Status before the transition, `None` if it was unknown.
+    pub from: Option<TradingStatus>,
+    pub to: TradingStatus,
+    /// Group symbol if the transition was caused by a group-level message.
+    pub group: Option<String>,
+    pub sending_time: Option<DateTime<Utc>>,
+}
+
+/// Registry of instruments' trading statuses.
+#[derive(Debug, Default)]
+pub struct StatusRegistry {
+    statuses: HashMap<u32, TradingStatus>,
+    groups: HashMap<String, TradingStatus>,
+    members: HashMap<String, Vec<u32>>,
+}
+
+impl StatusRegistry {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Assign the instrument to the group of its symbol.
+    pub fn register(&mut self, security_id: u32, symbol: &str) {
+        let members = self.members.entry(symbol.to_string()).or_default();
+        if !members.contains(&security_id) {
+            members.push(security_id);
+        }
+        if let Some(status) = self.groups.get(symbol) {
+            self.statuses.entry(security_id).or_insert(*status);
+        }
+    }
+
+    /// Assign the instrument to its group if the definition has a symbol.
+    pub fn add_definition(&mut self, definition: &SecurityDefinition) {
+        if let Some(symbol) = &definition.symbol {
+            self.register(definition.security_id, symbol);
+        }
+    }
+
+    /// Update statuses from a message and return transitions.
+    /// Security definitions register instruments in their groups.
+    pub fn process(&mut self, msg: &Message) -> Vec<StatusTransition> {
+        if let Message::MDSecurityDefinition(m) = msg {
+            self.add_definition(m);
+            return Vec::new();
+        }
+        normalize(msg)
+            .iter()
+            .flat_map(|event| match event {
+                MarketEvent::StatusChange(change) => self.process_change(change),
+                _ => Vec::new(),
+            })
+            .collect()
+    }
+
+    /// Update statuses from a status change and return transitions.
+    pub fn process_change(&mut self, change: &StatusChange) -> Vec<StatusTransition> {
+        if change.status == TradingStatus::NoChange {
+            return Vec::new();
+        }
+        match (change.security_id, &change.symbol) {
+            (Some(security_id), symbol) => {
+                if let Some(symbol) = symbol {
+                    self.register(security_id, symbol);
+                }
+                self.transition(security_id, change, None)
+                    .into_iter()
+                    .collect()
+            }
+            (None, Some(symbol)) => {
+                self.groups.insert(symbol.clone(), change.status);
+                let members = self.members.get(symbol).cloned().unwrap_or_default();
+                members
+                    .into_iter()
+                    .filter_map(|security_id| self.transition(security_id, change, Some(symbol)))
+                    .collect()
+            }
+            (None, None) => Vec::new(),
+        }
+    }
+
+    fn transition(
+        &mut self,
+        security_id: u32,
+        change: &StatusChange,
+        group: Option<&String>,
+    ) -> Option<StatusTransition> {
+        let from = self.statuses.insert(security_id, change.status);
+        (from != Some(change.status)).then(|| StatusTransition {
+            security_id,
+            from,
+            to: change.status,
+            group: group.cloned(),
+            sending_time: change.sending_time,
+        })
+    }
+
+    /// Current status of the instrument.
+    #[must_use]
+    pub fn status(&self, security_id: u32) -> Option<TradingStatus> {
+        self.statuses.get(&security_id).copied()
+    }
+
+    /// Last status of the group.
+    #[must_use]
+    pub fn group_status(&self, symbol: &str) -> Option<TradingStatus> {
+        self.groups.get(symbol).copied()
+    }
+
+    /// The instrument is tradable unless its known status says otherwise.
+    #[must_use]
+    pub fn is_tradable(&self, security_id: u32) -> bool {
+        self.status(security_id)
+            .is_none_or(TradingStatus::is_tradable)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn change(
+        security_id: Option<u32>,
+        symbol: Option<&str>,
+        status: TradingStatus,
+    ) -> StatusChange {
+        StatusChange {
+            security_id,
+            symbol: symbol.map(str::to_string),
+            status,
+            sending_time: None,
+        }
+    }
+
+    #[test]
+    fn test_instrument_status() {
+        let mut registry = StatusRegistry::new();
+        let transitions = registry.process_change(&change(Some(1), None, TradingStatus::PreOpen));
+        assert_eq!(transitions.len(), 1);
+        assert_eq!(transitions[0].from, None);
+        assert!(!registry.is_tradable(1));
+
+        let transitions =
+            registry.process_change(&change(Some(1), None, TradingStatus::ReadyToTrade));
+        assert_eq!(transitions[0].from, Some(TradingStatus::PreOpen));
+        assert!(registry.is_tradable(1));
+
+        // repeated and no-change statuses produce no transitions
+        assert!(
+            registry
+                .process_change(&change(Some(1), None, TradingStatus::ReadyToTrade))
+                .is_empty()
+        );
+        assert!(
+            registry
+                .process_change(&change(Some(1), None, TradingStatus::NoChange))
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_group_status() {
+        let mut registry = StatusRegistry::new();
+        registry.register(1, "EP");
+        registry.register(2, "EP");
+        registry.register(3, "ENQ");
+        registry.process_change(&change(Some(2), None, TradingStatus::Halt));
+
+        let transitions = registry.process_change(&change(None, Some("EP"), TradingStatus::Halt));
+        assert_eq!(transitions.len(), 1);
+        assert_eq!(transitions[0].security_id, 1);
+        assert_eq!(transitions[0].group.as_deref(), Some("EP"));
+        assert_eq!(registry.status(3), None);
+
+        registry.register(4, "EP");
+        assert_eq!(registry.status(4), Some(TradingStatus::Halt));
+        assert_eq!(registry.group_status("EP"), Some(TradingStatus::Halt));
+    }
+}
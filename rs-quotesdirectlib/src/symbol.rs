@@ -0,0 +1,310 @@
+//! # CQG symbols
+//!
+//! Parses and formats CQG symbology used in `SecurityDefinition.CQGSecurityName`:
+//! - futures: `F.US.EPZ25`
+//! - options: `C.US.EPZ256000` (call), `P.US.EPZ256000` (put), the strike follows the year;
+//! - spreads: `F.US.EPZ25-EPH26`, legs joined with `-`.
+//!
+//! The symbol consists of the instrument type letter, exchange prefix, root,
+//! contract month code and two-digit year.
+//!
+//! # Examples
+//!
+//! ```rust,ignore
+//! use quotesdirectlib::symbol::CqgSymbol;
+//!
+//! let symbol: CqgSymbol = "F.US.EPZ25".parse()?;
+//! assert_eq!(symbol.root(), "EP");
+//! let security_id = master.find_symbol(&symbol);
+//! ```
+//!
+use fastlib::Decimal;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+use crate::secmaster::SecurityMaster;
+use crate::{Error, Result};
+
+/// Futures month codes, January to December.
+const MONTH_CODES: &[u8; 12] = b"FGHJKMNQUVXZ";
+
+/// Instrument type.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum InstrumentType {
+    Future,
+    Call,
+    Put,
+    Spread,
+}
+
+/// Contract of a root for a delivery month.
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+pub struct Contract {
+    pub root: String,
+    /// Month, 1 to 12.
+    pub month: u32,
+    /// Four-digit year.
+    pub year: u32,
+}
+
+impl Contract {
+    /// Month code letter of the contract.
+    #[must_use]
+    pub fn month_code(&self) -> char {
+        let index = self.month.clamp(1, 12) as usize - 1;
+        char::from(MONTH_CODES[index])
+    }
+}
+
+impl Display for Contract {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}{}{:02}",
+            self.root,
+            self.month_code(),
+            self.year % 100
+        )
+    }
+}
+
+/// Parsed CQG symbol.
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+pub struct CqgSymbol {
+    pub instrument_type: InstrumentType,
+    /// Exchange prefix, e.g. `US`.
+    pub prefix: String,
+    /// One contract for futures and options, one per leg for spreads.
+    pub contracts: Vec<Contract>,
+    pub strike: Option<Decimal>,
+}
+
+impl CqgSymbol {
+    /// Root of the (first) contract.
+    #[must_use]
+    pub fn root(&self) -> &str {
+        self.contracts.first().map_or("", |c| c.root.as_str())
+    }
+
+    /// Month of the (first) contract.
+    #[must_use]
+    pub fn month(&self) -> u32 {
+        self.contracts.first().map_or(0, |c| c.month)
+    }
+
+    /// Year of the (first) contract.
+    #[must_use]
+    pub fn year(&self) -> u32 {
+        self.contracts.first().map_or(0, |c| c.year)
+    }
+}
+
+impl FromStr for CqgSymbol {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let invalid = || Error::InvalidSymbol(s.to_string());
+        let mut parts = s.splitn(3, '.');
+        let (Some(kind), Some(prefix), Some(body)) = (parts.next(), parts.next(), parts.next())
+        else {
+            return Err(invalid());
+        };
+        if prefix.is_empty() || !prefix.bytes().all(|b| b.is_ascii_alphanumeric()) {
+            return Err(invalid());
+        }
+        let instrument_type = match kind {
+            "F" if body.contains('-') => InstrumentType::Spread,
+            "F" => InstrumentType::Future,
+            "C" => InstrumentType::Call,
+            "P" => InstrumentType::Put,
+            _ => return Err(invalid()),
+        };
+        let (contracts, strike) = match instrument_type {
+            InstrumentType::Future => (vec![parse_contract(body).ok_or_else(invalid)?], None),
+            InstrumentType::Spread => (
+                body.split('-')
+                    .map(parse_contract)
+                    .collect::<Option<Vec<_>>>()
+                    .ok_or_else(invalid)?,
+                None,
+            ),
+            InstrumentType::Call | InstrumentType::Put => {
+                let (contract, strike) = parse_option(body).ok_or_else(invalid)?;
+                (vec![contract], Some(strike))
+            }
+        };
+        Ok(Self {
+            instrument_type,
+            prefix: prefix.to_string(),
+            contracts,
+            strike,
+        })
+    }
+}
+
+impl Display for CqgSymbol {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let kind = match self.instrument_type {
+            InstrumentType::Future | InstrumentType::Spread => 'F',
+            InstrumentType::Call => 'C',
+            InstrumentType::Put => 'P',
+        };
+        write!(f, "{kind}.{}.", self.prefix)?;
+        for (i, contract) in self.contracts.iter().enumerate() {
+            if i > 0 {
+                f.write_str("-")?;
+            }
+            write!(f, "{contract}")?;
+        }
+        if let Some(strike) = &self.strike {
+            write_strike(f, strike)?;
+        }
+        Ok(())
+    }
+}
+
+/// Parse `ROOT` + month code + two-digit year.
+fn parse_contract(s: &str) -> Option<Contract> {
+    let (contract, rest) = split_contract(s)?;
+    rest.is_empty().then_some(contract)
+}
+
+/// Parse `ROOT` + month code + two-digit year + strike.
+fn parse_option(s: &str) -> Option<(Contract, Decimal)> {
+    let (contract, strike) = split_contract(s)?;
+    let valid = !strike.is_empty()
+        && strike.bytes().all(|b| b.is_ascii_digit() || b == b'.')
+        && strike.bytes().filter(|b| *b == b'.').count() <= 1
+        && !strike.starts_with('.')
+        && !strike.ends_with('.');
+    if !valid {
+        return None;
+    }
+    Some((contract, Decimal::from_string(strike).ok()?))
+}
+
+/// Split the contract off the string at the first month code followed by two digits.
+fn split_contract(s: &str) -> Option<(Contract, &str)> {
+    let bytes = s.as_bytes();
+    (1..bytes.len().saturating_sub(2)).find_map(|i| {
+        let month = MONTH_CODES.iter().position(|c| *c == bytes[i])?;
+        let year = &s.get(i + 1..i + 3)?;
+        let root = &s[..i];
+        if !year.bytes().all(|b| b.is_ascii_digit())
+            || !root
+                .bytes()
+                .all(|b| b.is_ascii_uppercase() || b.is_ascii_digit())
+        {
+            return None;
+        }
+        let contract = Contract {
+            root: root.to_string(),
+            month: u32::try_from(month).ok()? + 1,
+            year: 2000 + year.parse::<u32>().ok()?,
+        };
+        Some((contract, &s[i + 3..]))
+    })
+}
+
+/// Write the strike without exponent notation or trailing `.0`.
+fn write_strike(f: &mut Formatter<'_>, strike: &Decimal) -> std::fmt::Result {
+    if strike.exponent >= 0 {
+        write!(f, "{}", strike.mantissa)?;
+        for _ in 0..strike.exponent {
+            f.write_str("0")?;
+        }
+        Ok(())
+    } else {
+        write!(f, "{strike}")
+    }
+}
+
+impl SecurityMaster {
+    /// `SecurityID` of the instrument with the CQG symbol.
+    #[must_use]
+    pub fn find_symbol(&self, symbol: &CqgSymbol) -> Option<u32> {
+        self.find_cqg_security_name(&symbol.to_string())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::fast::{SecurityDefinition, fixtures};
+
+    fn definition(security_id: u32, cqg_security_name: &str) -> SecurityDefinition {
+        SecurityDefinition {
+            cqg_security_name: Some(cqg_security_name.to_string()),
+            ..fixtures::definition(security_id)
+        }
+    }
+
+    #[test]
+    fn test_future() {
+        let symbol: CqgSymbol = "F.US.EPZ25".parse().unwrap();
+        assert_eq!(symbol.instrument_type, InstrumentType::Future);
+        assert_eq!(symbol.prefix, "US");
+        assert_eq!(symbol.root(), "EP");
+        assert_eq!((symbol.month(), symbol.year()), (12, 2025));
+        assert_eq!(symbol.strike, None);
+        assert_eq!(symbol.to_string(), "F.US.EPZ25");
+
+        let symbol: CqgSymbol = "F.US.ZUCF26".parse().unwrap();
+        assert_eq!(symbol.root(), "ZUC");
+        assert_eq!(symbol.month(), 1);
+    }
+
+    #[test]
+    fn test_option() {
+        let symbol: CqgSymbol = "C.US.EPZ256000".parse().unwrap();
+        assert_eq!(symbol.instrument_type, InstrumentType::Call);
+        assert_eq!(symbol.root(), "EP");
+        assert_eq!(symbol.strike, Some(Decimal::new(3, 6)));
+        assert_eq!(symbol.to_string(), "C.US.EPZ256000");
+
+        let symbol: CqgSymbol = "P.US.ZNEH26112.5".parse().unwrap();
+        assert_eq!(symbol.instrument_type, InstrumentType::Put);
+        assert_eq!(symbol.strike, Some(Decimal::new(-1, 1125)));
+        assert_eq!(symbol.to_string(), "P.US.ZNEH26112.5");
+    }
+
+    #[test]
+    fn test_spread() {
+        let symbol: CqgSymbol = "F.US.EPZ25-EPH26".parse().unwrap();
+        assert_eq!(symbol.instrument_type, InstrumentType::Spread);
+        assert_eq!(symbol.contracts.len(), 2);
+        assert_eq!(symbol.contracts[1].year, 2026);
+        assert_eq!(symbol.to_string(), "F.US.EPZ25-EPH26");
+    }
+
+    #[test]
+    fn test_invalid() {
+        for s in [
+            "",
+            "F.US",
+            "X.US.EPZ25",
+            "F.US.EPZ2",
+            "F.US.Z25",
+            "C.US.EPZ25",
+            "F.US.EPZ25-",
+        ] {
+            assert!(s.parse::<CqgSymbol>().is_err(), "{s}");
+        }
+    }
+
+    #[test]
+    fn test_find_symbol() {
+        let mut master = SecurityMaster::new();
+        master.insert(definition(1, "F.US.EPZ25"));
+        master.insert(definition(2, "F.US.EPH26"));
+        let symbol: CqgSymbol = "F.US.EPZ25".parse().unwrap();
+        assert_eq!(master.get(1).unwrap().cqg_symbol(), Some(symbol.clone()));
+        assert_eq!(master.find_symbol(&symbol), Some(1));
+
+        master.insert(definition(1, "F.US.EPM26"));
+        assert_eq!(master.find_symbol(&symbol), None);
+        assert_eq!(master.find_cqg_security_name("F.US.EPM26"), Some(1));
+        master.remove(2);
+        assert_eq!(master.find_cqg_security_name("F.US.EPH26"), None);
+    }
+}
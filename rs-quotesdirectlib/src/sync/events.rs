@@ -0,0 +1,76 @@
+//! Normalized market events iterator
+//!
+//! # Examples
+//!
+//! ```rust,ignore
+//! use quotesdirectlib::sync::events::MarketEvents;
+//!
+//! let messages: Vec<Message> = vec![...];
+//! for event in MarketEvents::new(messages.iter()) {
+//!     println!("{event:?}");
+//! }
+//! ```
+//!
+use std::borrow::Borrow;
+use std::collections::VecDeque;
+
+use crate::fast::Message;
+use crate::market::{MarketEvent, normalize_into};
+
+/// Iterator adapter that turns messages into normalized market events.
+pub struct MarketEvents<I> {
+    messages: I,
+    pending: VecDeque<MarketEvent>,
+    buffer: Vec<MarketEvent>,
+}
+
+impl<I> MarketEvents<I> {
+    pub fn new(messages: I) -> Self {
+        Self {
+            messages,
+            pending: VecDeque::new(),
+            buffer: Vec::new(),
+        }
+    }
+}
+
+impl<I, M> Iterator for MarketEvents<I>
+where
+    I: Iterator<Item = M>,
+    M: Borrow<Message>,
+{
+    type Item = MarketEvent;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(event) = self.pending.pop_front() {
+                return Some(event);
+            }
+            let msg = self.messages.next()?;
+            normalize_into(msg.borrow(), &mut self.buffer);
+            self.pending.extend(self.buffer.drain(..));
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::fast::fixtures::{inc_refresh, md_entry};
+
+    #[test]
+    fn test_events() {
+        let messages = [
+            inc_refresh(1, vec![md_entry(100, 1, None), md_entry(101, 1, None)]),
+            inc_refresh(2, vec![md_entry(102, 1, None)]),
+        ];
+        let mut events = MarketEvents::new(messages.iter());
+        for security_id in [100, 101, 102] {
+            let Some(MarketEvent::BookUpdate(update)) = events.next() else {
+                panic!("expected book update");
+            };
+            assert_eq!(update.security_id, security_id);
+        }
+        assert!(events.next().is_none());
+    }
+}
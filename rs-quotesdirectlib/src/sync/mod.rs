@@ -1,2 +1,3 @@
 //! # Synchronous versions of packets processing objects
+pub mod events;
 pub mod packets;
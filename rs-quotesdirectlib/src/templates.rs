@@ -0,0 +1,706 @@
+//! # Templates
+//!
+//! Loads FAST templates at runtime and checks whether the typed
+//! [`Message`](crate::fast::Message) structs can decode them.
+//!
+//! The structs are not inspected. They are defined after the bundled [`TEMPLATES_XML`],
+//! and a template set is compared with the bundled templates instead: it is compatible
+//! if every field of the bundled templates is present with the same type
+//! and mandatory fields stay mandatory.
+//! Fields added by a newer version are ignored when decoding, so they are allowed.
+//! A message of a template added by a newer version has no variant in the `Message` enum
+//! and fails to decode, so new templates are reported; such messages can still be decoded
+//! with [`MessageDecoder::decode_any`](crate::decoder::MessageDecoder::decode_any).
+//! Field operators and template IDs do not affect the structs and are not compared.
+//!
+//! # Examples
+//!
+//! ```rust,ignore
+//! use quotesdirectlib::decoder::{MessageDecoder, ResetPolicy};
+//! use quotesdirectlib::templates::TemplateSet;
+//!
+//! let templates = TemplateSet::from_file(Path::new("templates.xml"))?;
+//! println!("templates version {:?}", templates.version());
+//! templates.check()?;
+//! let mut decoder = MessageDecoder::with_template_set(&templates, ResetPolicy::PerPacket)?;
+//! ```
+//!
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::path::Path;
+
+use roxmltree::{Document, Node};
+
+use crate::fast::TEMPLATES_XML;
+use crate::{Error, Result};
+
+/// Maximum depth of nested `templateRef`s.
+const MAX_DEPTH: usize = 16;
+
+/// Field type.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum FieldKind {
+    String,
+    ByteVector,
+    Int32,
+    UInt32,
+    Int64,
+    UInt64,
+    Decimal,
+    Sequence(Vec<Field>),
+    Group(Vec<Field>),
+}
+
+impl Display for FieldKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            FieldKind::String => "string",
+            FieldKind::ByteVector => "byteVector",
+            FieldKind::Int32 => "int32",
+            FieldKind::UInt32 => "uInt32",
+            FieldKind::Int64 => "int64",
+            FieldKind::UInt64 => "uInt64",
+            FieldKind::Decimal => "decimal",
+            FieldKind::Sequence(_) => "sequence",
+            FieldKind::Group(_) => "group",
+        })
+    }
+}
+
+/// Template field; static `templateRef`s are expanded in place.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Field {
+    pub name: String,
+    pub kind: FieldKind,
+    pub optional: bool,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Template {
+    /// `None` for templates only referenced by other templates.
+    pub id: Option<u32>,
+    pub name: String,
+    pub fields: Vec<Field>,
+}
+
+/// Difference between a template set and the typed messages.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum MismatchKind {
+    MissingTemplate,
+    /// Message template without a typed message.
+    NewTemplate,
+    MissingField,
+    TypeChanged {
+        expected: String,
+        found: String,
+    },
+    /// Mandatory field became optional.
+    BecameOptional,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Mismatch {
+    pub template: String,
+    /// Path of the field, e.g. `MDEntries.MDEntryPx`.
+    pub field: Option<String>,
+    pub kind: MismatchKind,
+}
+
+impl Display for Mismatch {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.template)?;
+        if let Some(field) = &self.field {
+            write!(f, ".{field}")?;
+        }
+        match &self.kind {
+            MismatchKind::MissingTemplate => f.write_str(": template is missing"),
+            MismatchKind::NewTemplate => f.write_str(": template has no typed message"),
+            MismatchKind::MissingField => f.write_str(": field is missing"),
+            MismatchKind::TypeChanged { expected, found } => {
+                write!(f, ": expected {expected} but found {found}")
+            }
+            MismatchKind::BecameOptional => f.write_str(": mandatory field became optional"),
+        }
+    }
+}
+
+/// Parsed FAST templates.
+#[derive(Debug, Clone)]
+pub struct TemplateSet {
+    xml: String,
+    version: Option<u32>,
+    templates: Vec<Template>,
+}
+
+impl TemplateSet {
+    /// Templates the typed messages are defined after.
+    /// # Panics
+    /// Never, the bundled templates are valid.
+    #[must_use]
+    pub fn bundled() -> Self {
+        Self::from_xml(TEMPLATES_XML).expect("bundled templates")
+    }
+
+    /// Load templates from a file.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or the templates cannot be parsed.
+    pub fn from_file(path: &Path) -> Result<Self> {
+        Self::from_xml(std::fs::read_to_string(path)?)
+    }
+
+    /// Parse templates XML.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the templates cannot be parsed.
+    pub fn from_xml(xml: impl Into<String>) -> Result<Self> {
+        let xml = xml.into();
+        let (version, templates) = parse(&xml)?;
+        Ok(Self {
+            xml,
+            version,
+            templates,
+        })
+    }
+
+    /// Source XML, for creating decoders.
+    #[must_use]
+    pub fn xml(&self) -> &str {
+        &self.xml
+    }
+
+    /// Version from the `<!-- Version N -->` comment.
+    #[must_use]
+    pub fn version(&self) -> Option<u32> {
+        self.version
+    }
+
+    #[must_use]
+    pub fn templates(&self) -> &[Template] {
+        &self.templates
+    }
+
+    #[must_use]
+    pub fn get(&self, name: &str) -> Option<&Template> {
+        self.templates.iter().find(|t| t.name == name)
+    }
+
+    /// Differences from the bundled templates the typed messages are defined after.
+    #[must_use]
+    pub fn mismatches(&self) -> Vec<Mismatch> {
+        let bundled = Self::bundled();
+        let mut mismatches = Vec::new();
+        for expected in bundled.templates.iter().filter(|t| t.id.is_some()) {
+            match self.get(&expected.name) {
+                Some(found) => compare(
+                    &expected.name,
+                    "",
+                    &expected.fields,
+                    &found.fields,
+                    &mut mismatches,
+                ),
+                None => mismatches.push(Mismatch {
+                    template: expected.name.clone(),
+                    field: None,
+                    kind: MismatchKind::MissingTemplate,
+                }),
+            }
+        }
+        for found in self.templates.iter().filter(|t| t.id.is_some()) {
+            if bundled.get(&found.name).is_none_or(|t| t.id.is_none()) {
+                mismatches.push(Mismatch {
+                    template: found.name.clone(),
+                    field: None,
+                    kind: MismatchKind::NewTemplate,
+                });
+            }
+        }
+        mismatches
+    }
+
+    /// Check the templates can be decoded into the typed messages,
+    /// see [`TemplateSet::mismatches`].
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::TemplateMismatch`] listing all differences.
+    pub fn check(&self) -> Result<()> {
+        let mismatches = self.mismatches();
+        if mismatches.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::TemplateMismatch(mismatches))
+        }
+    }
+}
+
+fn compare(
+    template: &str,
+    prefix: &str,
+    expected: &[Field],
+    found: &[Field],
+    mismatches: &mut Vec<Mismatch>,
+) {
+    for field in expected {
+        let path = format!("{prefix}{}", field.name);
+        let mut mismatch = |kind| {
+            mismatches.push(Mismatch {
+                template: template.to_string(),
+                field: Some(path.clone()),
+                kind,
+            });
+        };
+        let Some(other) = found.iter().find(|f| f.name == field.name) else {
+            // missing optional field is deserialized as `None`
+            if !field.optional {
+                mismatch(MismatchKind::MissingField);
+            }
+            continue;
+        };
+        if other.optional && !field.optional {
+            mismatch(MismatchKind::BecameOptional);
+        }
+        match (&field.kind, &other.kind) {
+            (FieldKind::Sequence(expected), FieldKind::Sequence(found))
+            | (FieldKind::Group(expected), FieldKind::Group(found)) => {
+                compare(template, &format!("{path}."), expected, found, mismatches);
+            }
+            (expected, found) if expected.to_string() != found.to_string() => {
+                mismatch(MismatchKind::TypeChanged {
+                    expected: expected.to_string(),
+                    found: found.to_string(),
+                });
+            }
+            _ => {}
+        }
+    }
+}
+
+type Parsed = (Option<u32>, Vec<Template>);
+
+fn parse(xml: &str) -> Result<Parsed> {
+    let doc = Document::parse(xml).map_err(|err| Error::InvalidTemplates(err.to_string()))?;
+    let root = doc.root_element();
+    if root.tag_name().name() != "templates" {
+        return Err(Error::InvalidTemplates(format!(
+            "unexpected root element <{}>",
+            root.tag_name().name()
+        )));
+    }
+    let version = root
+        .children()
+        .filter(Node::is_comment)
+        .find_map(|node| node.text()?.trim().strip_prefix("Version ")?.parse().ok());
+    let nodes: HashMap<&str, Node> = root
+        .children()
+        .filter(|node| node.has_tag_name("template"))
+        .filter_map(|node| Some((node.attribute("name")?, node)))
+        .collect();
+
+    let mut templates = Vec::new();
+    for node in root.children().filter(|node| node.has_tag_name("template")) {
+        let name = attribute(node, "name")?;
+        let id = node
+            .attribute("id")
+            .map(|id| {
+                id.parse().map_err(|_| {
+                    Error::InvalidTemplates(format!("template {name}: invalid id {id}"))
+                })
+            })
+            .transpose()?;
+        templates.push(Template {
+            id,
+            name: name.to_string(),
+            fields: parse_fields(node, &nodes, 0)?,
+        });
+    }
+    Ok((version, templates))
+}
+
+fn parse_fields(node: Node, templates: &HashMap<&str, Node>, depth: usize) -> Result<Vec<Field>> {
+    if depth > MAX_DEPTH {
+        return Err(Error::InvalidTemplates(
+            "templateRef nested too deep".to_string(),
+        ));
+    }
+    let mut fields = Vec::new();
+    for child in node.children().filter(Node::is_element) {
+        let kind = match child.tag_name().name() {
+            "string" => FieldKind::String,
+            "byteVector" => FieldKind::ByteVector,
+            "int32" => FieldKind::Int32,
+            "uInt32" => FieldKind::UInt32,
+            "int64" => FieldKind::Int64,
+            "uInt64" => FieldKind::UInt64,
+            "decimal" => FieldKind::Decimal,
+            "sequence" => FieldKind::Sequence(parse_fields(child, templates, depth + 1)?),
+            "group" => FieldKind::Group(parse_fields(child, templates, depth + 1)?),
+            "templateRef" => {
+                // dynamic references cannot be checked statically
+                let Some(name) = child.attribute("name") else {
+                    continue;
+                };
+                let template = templates.get(name).ok_or_else(|| {
+                    Error::InvalidTemplates(format!("templateRef to unknown template {name}"))
+                })?;
+                fields.extend(parse_fields(*template, templates, depth + 1)?);
+                continue;
+            }
+            // `length`, operators and other instructions
+            _ => continue,
+        };
+        fields.push(Field {
+            name: attribute(child, "name")?.to_string(),
+            kind,
+            optional: child.attribute("presence") == Some("optional"),
+        });
+    }
+    Ok(fields)
+}
+
+fn attribute<'a>(node: Node<'a, '_>, name: &str) -> Result<&'a str> {
+    node.attribute(name).ok_or_else(|| {
+        Error::InvalidTemplates(format!(
+            "<{}> without {name} attribute",
+            node.tag_name().name()
+        ))
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::decoder::{MessageDecoder, ResetPolicy};
+    use crate::fast::fixtures::{definition, header, heartbeat, md_entry};
+    use crate::fast::*;
+    use fastlib::{Decimal, Encoder};
+    use std::collections::BTreeSet;
+
+    fn connection() -> Connection {
+        Connection {
+            connection_type: 1,
+            connection_ip_address: "224.0.0.1".to_string(),
+            connection_port_number: 5000,
+        }
+    }
+
+    /// Every message type with all fields set.
+    /// Byte vectors are left out, the typed structs cannot be encoded with them.
+    fn all_messages() -> Vec<Message> {
+        let text = || Some("text".to_string());
+        vec![
+            Message::MDIncRefresh(IncRefresh {
+                message_type: "X".to_string(),
+                msg_header: header(1),
+                trade_date: Some(20250620),
+                md_entries: vec![MDEntry {
+                    md_entry_type: "2".to_string(),
+                    md_entry_size: Some(3),
+                    quote_condition: Some("K".to_string()),
+                    md_quote_type: Some(1),
+                    trade_condition: Some("1".to_string()),
+                    trade_volume: Some(10),
+                    aggressor_side: Some(1),
+                    md_workup_state: Some(1),
+                    parties: Some(vec![Party {
+                        party_id: 1,
+                        party_id_source: "D".to_string(),
+                    }]),
+                    ..md_entry(100, 1, Some(Decimal::new(-2, 450_025)))
+                }],
+            }),
+            Message::MDSecurityDefinition(SecurityDefinition {
+                events: Some(vec![Event {
+                    event_type: 5,
+                    event_date: 20250620,
+                    event_time: 20250620220000000,
+                }]),
+                security_group: Some("EP".to_string()),
+                symbol: Some("EP".to_string()),
+                security_name: "EPU5".to_string(),
+                security_desc: "E-mini S&P 500".to_string(),
+                security_id_source: 100,
+                security_exchange: Some("XCME".to_string()),
+                cqg_security_name: Some("F.US.EPU25".to_string()),
+                strike_price: Some(Decimal::new(0, 6000)),
+                strike_currency: Some("USD".to_string()),
+                currency: Some("USD".to_string()),
+                settl_currency: Some("USD".to_string()),
+                md_feed_types: Some(vec![FeedType {
+                    feed_type: "GBX".to_string(),
+                    market_depth: 10,
+                }]),
+                instr_attrib: Some(vec![InstrAttrib {
+                    instr_attrib_type: 24,
+                    instr_attrib_value: Some("Y".to_string()),
+                }]),
+                maturity_month_year: Some(202509),
+                min_price_increment: Some(0.25),
+                min_price_increment_amount: Some(12.5),
+                display_factor: Some(Decimal::new(-2, 1)),
+                most_active_flag: Some("Y".to_string()),
+                connections: vec![connection()],
+                trading_sessions: vec![TradingSession {
+                    trade_date: 20250620,
+                    trad_ses_start_time: 20250619220000000,
+                    trad_ses_open_time: 20250619220000000,
+                    trad_ses_close_time: 20250620210000000,
+                    trad_ses_end_time: 20250620210000000,
+                }],
+                underlyings: Some(vec![Underlying {
+                    security_id: 99,
+                    security_id_source: 100,
+                }]),
+                security_sub_type: Some("SP".to_string()),
+                legs: Some(vec![Leg {
+                    leg_symbol: "EP".to_string(),
+                    leg_security_desc: "EPU5".to_string(),
+                    leg_ratio_qty: Decimal::new(0, 1),
+                    leg_security_id: 101,
+                    leg_security_id_source: 100,
+                    leg_side: 1,
+                    leg_security_group: "EP".to_string(),
+                    leg_cfi_code: "FFIXSX".to_string(),
+                    leg_currency: "USD".to_string(),
+                    leg_maturity_month_year: 202509,
+                    leg_strike_price: Decimal::new(0, 6000),
+                }]),
+                ..definition(100)
+            }),
+            Message::MDSnapshotFullRefresh(SnapshotFullRefresh {
+                message_type: "W".to_string(),
+                msg_header: header(1),
+                last_msg_seq_num_processed: 1,
+                tot_num_reports: 1,
+                rpt_seq: 1,
+                security_id: 100,
+                security_id_source: 100,
+                md_security_trading_status: Some(17),
+                md_entries: vec![MDEntrySnapshot {
+                    md_entry_type: "0".to_string(),
+                    md_entry_px: Some(Decimal::new(-2, 450_025)),
+                    md_entry_size: Some(3),
+                    quote_condition: Some("K".to_string()),
+                    md_price_level: Some(1),
+                    md_workup_state: Some(1),
+                }],
+            }),
+            heartbeat(1),
+            Message::MDLogon(Logon {
+                message_type: "A".to_string(),
+                msg_header: header(1),
+                encrypt_method: 0,
+                heartbeat_int: 30,
+            }),
+            Message::MDLogout(Logout {
+                message_type: "5".to_string(),
+                msg_header: header(1),
+                text: text(),
+            }),
+            Message::MDSecurityDefinitionRequest(SecurityDefinitionRequest {
+                message_type: "c".to_string(),
+                msg_header: header(1),
+                appl_id: "1".to_string(),
+                text: text(),
+            }),
+            Message::SequenceReset(SequenceReset {
+                message_type: "4".to_string(),
+                msg_header: header(1),
+                new_seq_no: 100,
+            }),
+            Message::MDSecurityStatus(SecurityStatus {
+                message_type: "f".to_string(),
+                msg_header: header(1),
+                security_id: Some(100),
+                security_id_source: Some(100),
+                symbol: Some("EP".to_string()),
+                security_trading_status: Some(17),
+            }),
+            Message::News(News {
+                message_type: "B".to_string(),
+                msg_header: header(1),
+                message_encoding: "UTF-8".to_string(),
+                appl_id: "N".to_string(),
+                news_id: "1".to_string(),
+                news_source_id: 1,
+                last_fragment: Some("Y".to_string()),
+                news_ref_ids: Some(vec![NewsRefID {
+                    news_ref_id: "0".to_string(),
+                    news_ref_type: 0,
+                }]),
+                orig_time: Some(20250620102247123),
+                urgency: Some("0".to_string()),
+                news_branding: Some("CQG".to_string()),
+                accession_number: Some("1".to_string()),
+                encoded_headline: None,
+                encoded_text: None,
+                news_categories: Some(vec![NewsCategory {
+                    category_class: 1,
+                    category_code: "EN".to_string(),
+                }]),
+            }),
+            Message::ApplicationMessageRequestAck(ApplicationMessageRequestAck {
+                message_type: "BX".to_string(),
+                msg_header: header(1),
+                appl_response_id: "1".to_string(),
+                appl_req_id: "1".to_string(),
+                appl_ids: vec![ApplID {
+                    ref_appl_id: "1".to_string(),
+                    appl_response_error: Some(0),
+                    raw_data: None,
+                    news_source_id: Some(1),
+                    connections: vec![connection()],
+                }],
+            }),
+            Message::UserNotification(UserNotification {
+                message_type: "CB".to_string(),
+                msg_header: header(1),
+                user_status: 1,
+                text: "text".to_string(),
+            }),
+        ]
+    }
+
+    /// Every template field but byte vectors is serialized from the typed message,
+    /// so the structs cover the template.
+    fn assert_fields(path: &str, expected: &[Field], value: &serde_json::Value) {
+        for field in expected.iter().filter(|f| f.kind != FieldKind::ByteVector) {
+            let path = format!("{path}.{}", field.name);
+            let value = value
+                .get(&field.name)
+                .unwrap_or_else(|| panic!("{path} is not in the typed message"));
+            match &field.kind {
+                FieldKind::Sequence(expected) => {
+                    for item in value.as_array().into_iter().flatten() {
+                        assert_fields(&path, expected, item);
+                    }
+                }
+                FieldKind::Group(expected) => assert_fields(&path, expected, value),
+                _ => {}
+            }
+        }
+    }
+
+    #[test]
+    fn test_bundled_matches_structs() {
+        let templates = TemplateSet::bundled();
+        templates.check().unwrap();
+        let mut encoder = Encoder::new_from_xml(templates.xml()).unwrap();
+        let mut decoder =
+            MessageDecoder::with_template_set(&templates, ResetPolicy::PerMessage).unwrap();
+
+        let mut names = BTreeSet::new();
+        for msg in all_messages() {
+            encoder.reset();
+            let raw = fastlib::to_vec(&mut encoder, &msg).unwrap();
+            let (decoded, size) = decoder.decode(&raw).unwrap();
+            assert_eq!(size, raw.len());
+            assert_eq!(decoded, msg);
+
+            // externally tagged by the template name
+            let json = serde_json::to_value(&msg).unwrap();
+            let (name, value) = json.as_object().unwrap().iter().next().unwrap();
+            let template = templates.get(name).unwrap();
+            assert_fields(name, &template.fields, value);
+            names.insert(name.clone());
+        }
+        // every message template is covered
+        let expected: BTreeSet<_> = templates
+            .templates()
+            .iter()
+            .filter(|t| t.id.is_some())
+            .map(|t| t.name.clone())
+            .collect();
+        assert_eq!(names, expected);
+    }
+
+    #[test]
+    fn test_bundled() {
+        let templates = TemplateSet::bundled();
+        assert_eq!(templates.version(), Some(7));
+        assert_eq!(templates.templates().len(), 13);
+        assert_eq!(templates.get("MsgHeader").unwrap().id, None);
+
+        // header is expanded in place
+        let heartbeat = templates.get("MDHeartbeat").unwrap();
+        assert_eq!(heartbeat.id, Some(4));
+        let names: Vec<_> = heartbeat.fields.iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(
+            names,
+            [
+                "MessageType",
+                "ApplVerID",
+                "SenderCompID",
+                "MsgSeqNum",
+                "SendingTime"
+            ]
+        );
+        assert!(templates.check().is_ok());
+    }
+
+    #[test]
+    fn test_mismatches() {
+        let xml = TEMPLATES_XML
+            .replace("<!-- Version 7 -->", "<!-- Version 8 -->")
+            // new optional field is compatible
+            .replace(
+                r#"<uInt32 id="75" name="TradeDate" presence="optional"/>"#,
+                r#"<uInt32 id="75" name="TradeDate" presence="optional"/>
+                   <string id="9999" name="NewField" presence="optional"/>"#,
+            )
+            .replace(
+                r#"<uInt32 id="83" name="RptSeq">
+                <increment/>
+            </uInt32>"#,
+                r#"<uInt64 id="83" name="RptSeq">
+                <increment/>
+            </uInt64>"#,
+            )
+            .replace(r#"<uInt64 id="52" name="SendingTime"/>"#, "")
+            .replace(
+                "</templates>",
+                r#"<template name="MDNewMessage" id="99"><string name="Text"/></template>
+                </templates>"#,
+            );
+        let templates = TemplateSet::from_xml(xml).unwrap();
+        assert_eq!(templates.version(), Some(8));
+
+        let mismatches = templates.mismatches();
+        assert!(mismatches.contains(&Mismatch {
+            template: "MDIncRefresh".to_string(),
+            field: Some("MDEntries.RptSeq".to_string()),
+            kind: MismatchKind::TypeChanged {
+                expected: "uInt32".to_string(),
+                found: "uInt64".to_string()
+            },
+        }));
+        // header field missing from every message
+        assert_eq!(
+            mismatches
+                .iter()
+                .filter(|m| m.field.as_deref() == Some("SendingTime"))
+                .count(),
+            12
+        );
+        assert!(!mismatches.iter().any(|m| m.template == "MsgHeader"));
+
+        let err = templates.check().unwrap_err().to_string();
+        assert!(err.contains("MDIncRefresh.MDEntries.RptSeq: expected uInt32 but found uInt64"));
+        assert!(err.contains("MDHeartbeat.SendingTime: field is missing"));
+        assert!(err.contains("MDNewMessage: template has no typed message"));
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert!(matches!(
+            TemplateSet::from_xml("<templates><template/></templates>"),
+            Err(Error::InvalidTemplates(_))
+        ));
+        assert!(matches!(
+            TemplateSet::from_xml("<templates>"),
+            Err(Error::InvalidTemplates(_))
+        ));
+    }
+}
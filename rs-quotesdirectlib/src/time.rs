@@ -0,0 +1,79 @@
+//! # Timestamps used by Quotes Direct API
+//!
+//! FAST messages carry timestamps as integers in decimal notation:
+//! - `UTCTimestamp` (e.g. `SendingTime`) as `YYYYMMDDHHMMSSsss`;
+//! - `UTCTimeOnly` (e.g. `MDEntryTime`) as `HHMMSSsss`;
+//! - `LocalMktDate` (e.g. `TradeDate`) as `YYYYMMDD`.
+//!
+use chrono::{DateTime, Duration, NaiveDate, NaiveTime, TimeZone, Utc};
+
+/// Convert `UTCTimestamp` (`YYYYMMDDHHMMSSsss`) to date and time.
+///
+/// # Examples
+///
+/// ```
+/// use quotesdirectlib::time::utc_timestamp;
+/// let ts = utc_timestamp(20250620102247123).unwrap();
+/// assert_eq!(ts.to_rfc3339(), "2025-06-20T10:22:47.123+00:00");
+/// ```
+#[must_use]
+pub fn utc_timestamp(value: u64) -> Option<DateTime<Utc>> {
+    let date = local_mkt_date(value / 1_000_000_000)?;
+    let time = utc_time_only((value % 1_000_000_000) as u32)?;
+    Some(Utc.from_utc_datetime(&date.and_time(time)))
+}
+
+/// Convert `UTCTimeOnly` (`HHMMSSsss`) to time.
+#[must_use]
+pub fn utc_time_only(value: u32) -> Option<NaiveTime> {
+    let millis = value % 1000;
+    let secs = (value / 1000) % 100;
+    let mins = (value / 100_000) % 100;
+    let hours = value / 10_000_000;
+    NaiveTime::from_hms_milli_opt(hours, mins, secs, millis)
+}
+
+/// Convert `LocalMktDate` (`YYYYMMDD`) to date.
+#[must_use]
+pub fn local_mkt_date(value: u64) -> Option<NaiveDate> {
+    let year = i32::try_from(value / 10_000).ok()?;
+    #[allow(clippy::cast_possible_truncation)]
+    let (month, day) = (((value / 100) % 100) as u32, (value % 100) as u32);
+    NaiveDate::from_ymd_opt(year, month, day)
+}
+
+/// Combine `UTCTimeOnly` with the date of a reference timestamp (usually `SendingTime`).
+///
+/// If the time is far ahead of the reference, it is assumed to belong to the previous day
+/// (e.g. entry time 23:59:59.900 in a message sent at 00:00:00.100).
+#[must_use]
+pub fn utc_time_on(reference: DateTime<Utc>, value: u32) -> Option<DateTime<Utc>> {
+    let time = utc_time_only(value)?;
+    let ts = Utc.from_utc_datetime(&reference.date_naive().and_time(time));
+    if ts - reference > Duration::hours(12) {
+        Some(ts - Duration::days(1))
+    } else {
+        Some(ts)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_utc_timestamp() {
+        let ts = utc_timestamp(20250620235959999).unwrap();
+        assert_eq!(ts.to_rfc3339(), "2025-06-20T23:59:59.999+00:00");
+        assert!(utc_timestamp(20251320000000000).is_none());
+    }
+
+    #[test]
+    fn test_utc_time_on() {
+        let sending = utc_timestamp(20250621000000100).unwrap();
+        let ts = utc_time_on(sending, 235959900).unwrap();
+        assert_eq!(ts.to_rfc3339(), "2025-06-20T23:59:59.900+00:00");
+        let ts = utc_time_on(sending, 50).unwrap();
+        assert_eq!(ts.to_rfc3339(), "2025-06-21T00:00:00.050+00:00");
+    }
+}
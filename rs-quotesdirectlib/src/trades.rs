@@ -0,0 +1,286 @@
+//! # Trade tape
+//!
+//! Assembles [`Trade`] records from trade entries of `MDIncRefresh` messages:
+//! - aggressor side and party ids;
+//! - cumulative volume per instrument and trade date;
+//! - grouping of workup trades (`MDWorkupState`) under one workup id;
+//! - dropping trades replayed during recovery, detected by `RptSeq`.
+//!
+//! `RptSeq` restarts with a new trade date, so the last one is forgotten on the rollover,
+//! on a snapshot and on `SequenceReset`.
+//!
+//! # Examples
+//!
+//! ```rust,ignore
+//! use quotesdirectlib::trades::TradeExtractor;
+//!
+//! let mut tape = TradeExtractor::new();
+//! for trade in tape.process(&msg) {
+//!     println!("{trade:?}");
+//! }
+//! ```
+//!
+use chrono::{DateTime, NaiveDate, Utc};
+use fastlib::Decimal;
+use std::collections::HashMap;
+
+use crate::fast::Message;
+use crate::market::{Aggressor, MarketEvent, TradeEvent, normalize};
+
+/// `MDWorkupState` (20016) values.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum WorkupState {
+    Start,
+    Continue,
+    End,
+    Other(u32),
+}
+
+impl WorkupState {
+    /// Returns `None` if the trade is not part of a workup.
+    #[must_use]
+    pub fn from_fix(value: u32) -> Option<Self> {
+        match value {
+            0 => None,
+            1 => Some(WorkupState::Start),
+            2 => Some(WorkupState::Continue),
+            3 => Some(WorkupState::End),
+            _ => Some(WorkupState::Other(value)),
+        }
+    }
+}
+
+/// Workup the trade belongs to.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub struct Workup {
+    /// Id shared by all trades of one workup.
+    pub id: u64,
+    pub state: WorkupState,
+}
+
+/// Trade tape record.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Trade {
+    pub security_id: u32,
+    pub rpt_seq: u32,
+    pub trade_date: Option<NaiveDate>,
+    pub time: Option<DateTime<Utc>>,
+    pub price: Decimal,
+    pub size: i32,
+    pub aggressor: Aggressor,
+    /// Instrument's volume for the trade date including this trade.
+    pub cumulative_volume: u64,
+    pub workup: Option<Workup>,
+    pub trade_condition: Option<String>,
+    pub party_ids: Vec<u32>,
+}
+
+#[derive(Debug, Default)]
+struct InstrumentState {
+    last_rpt_seq: Option<u32>,
+    trade_date: Option<NaiveDate>,
+    volume: u64,
+    workup: Option<u64>,
+}
+
+/// Extracts trades from market data messages.
+#[derive(Debug, Default)]
+pub struct TradeExtractor {
+    instruments: HashMap<u32, InstrumentState>,
+    next_workup_id: u64,
+    duplicates: u64,
+}
+
+impl TradeExtractor {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Extract trades from a message.
+    ///
+    /// Snapshots do not produce trades but set the instrument's `RptSeq`,
+    /// so incremental trades already reflected in the snapshot are dropped.
+    pub fn process(&mut self, msg: &Message) -> Vec<Trade> {
+        match msg {
+            Message::MDSnapshotFullRefresh(m) => {
+                let state = self.instruments.entry(m.security_id).or_default();
+                state.last_rpt_seq = Some(m.rpt_seq);
+                return Vec::new();
+            }
+            Message::SequenceReset(_) => {
+                for state in self.instruments.values_mut() {
+                    state.last_rpt_seq = None;
+                }
+                return Vec::new();
+            }
+            _ => {}
+        }
+        normalize(msg)
+            .iter()
+            .filter_map(|event| match event {
+                MarketEvent::Trade(trade) => self.process_trade(trade),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Build a trade record from a trade event.
+    /// Returns `None` if the trade is a duplicate or has no price.
+    /// Trades of a trade date before the instrument's current one are duplicates.
+    pub fn process_trade(&mut self, event: &TradeEvent) -> Option<Trade> {
+        let state = self.instruments.entry(event.security_id).or_default();
+        if event.trade_date.is_some() && event.trade_date > state.trade_date {
+            state.trade_date = event.trade_date;
+            state.last_rpt_seq = None;
+            state.volume = 0;
+            state.workup = None;
+        }
+        if event
+            .trade_date
+            .is_some_and(|date| state.trade_date > Some(date))
+            || state.last_rpt_seq.is_some_and(|seq| event.rpt_seq <= seq)
+        {
+            self.duplicates += 1;
+            return None;
+        }
+        state.last_rpt_seq = Some(event.rpt_seq);
+
+        let price = event.price.clone()?;
+        let size = event.size.unwrap_or(0);
+        state.volume = match event.trade_volume {
+            Some(volume) => u64::from(volume),
+            None => state.volume + u64::from(size.unsigned_abs()),
+        };
+
+        let workup = event
+            .workup_state
+            .and_then(WorkupState::from_fix)
+            .map(|workup_state| {
+                let id = match (workup_state, state.workup) {
+                    (WorkupState::Start, _) | (_, None) => {
+                        self.next_workup_id += 1;
+                        self.next_workup_id
+                    }
+                    (_, Some(id)) => id,
+                };
+                state.workup = if workup_state == WorkupState::End {
+                    None
+                } else {
+                    Some(id)
+                };
+                Workup {
+                    id,
+                    state: workup_state,
+                }
+            });
+
+        Some(Trade {
+            security_id: event.security_id,
+            rpt_seq: event.rpt_seq,
+            trade_date: state.trade_date,
+            time: event.time,
+            price,
+            size,
+            aggressor: event.aggressor.unwrap_or(Aggressor::None),
+            cumulative_volume: state.volume,
+            workup,
+            trade_condition: event.trade_condition.clone(),
+            party_ids: event.party_ids.clone(),
+        })
+    }
+
+    /// Number of trades dropped as duplicates.
+    #[must_use]
+    pub fn duplicates(&self) -> u64 {
+        self.duplicates
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn trade(rpt_seq: u32, size: i32, workup_state: Option<u32>) -> TradeEvent {
+        TradeEvent {
+            security_id: 1,
+            rpt_seq,
+            trade_date: NaiveDate::from_ymd_opt(2025, 6, 20),
+            price: Some(Decimal::new(-2, 10025)),
+            size: Some(size),
+            aggressor: Some(Aggressor::Sell),
+            trade_volume: None,
+            workup_state,
+            trade_condition: None,
+            party_ids: vec![],
+            time: None,
+            sending_time: None,
+        }
+    }
+
+    #[test]
+    fn test_cumulative_volume_and_duplicates() {
+        let mut tape = TradeExtractor::new();
+        assert_eq!(
+            tape.process_trade(&trade(1, 2, None))
+                .unwrap()
+                .cumulative_volume,
+            2
+        );
+        assert_eq!(
+            tape.process_trade(&trade(2, 3, None))
+                .unwrap()
+                .cumulative_volume,
+            5
+        );
+        assert!(tape.process_trade(&trade(2, 3, None)).is_none());
+        assert_eq!(tape.duplicates(), 1);
+
+        let mut next_day = trade(3, 4, None);
+        next_day.trade_date = NaiveDate::from_ymd_opt(2025, 6, 23);
+        assert_eq!(tape.process_trade(&next_day).unwrap().cumulative_volume, 4);
+    }
+
+    #[test]
+    fn test_rollover() {
+        let mut tape = TradeExtractor::new();
+        assert!(tape.process_trade(&trade(100, 2, None)).is_some());
+        assert!(tape.process_trade(&trade(101, 3, None)).is_some());
+
+        // RptSeq restarts with the new trade date
+        let mut next_day = trade(1, 4, None);
+        next_day.trade_date = NaiveDate::from_ymd_opt(2025, 6, 23);
+        let next_day = tape.process_trade(&next_day).unwrap();
+        assert_eq!(next_day.rpt_seq, 1);
+        assert_eq!(next_day.cumulative_volume, 4);
+
+        // replayed trade of the previous day
+        assert!(tape.process_trade(&trade(102, 1, None)).is_none());
+        assert_eq!(tape.duplicates(), 1);
+    }
+
+    #[test]
+    fn test_workup() {
+        let mut tape = TradeExtractor::new();
+        let first = tape.process_trade(&trade(1, 1, Some(1))).unwrap().workup;
+        let second = tape.process_trade(&trade(2, 1, Some(2))).unwrap().workup;
+        let last = tape.process_trade(&trade(3, 1, Some(3))).unwrap().workup;
+        let next = tape.process_trade(&trade(4, 1, Some(1))).unwrap().workup;
+        assert_eq!(
+            first,
+            Some(Workup {
+                id: 1,
+                state: WorkupState::Start
+            })
+        );
+        assert_eq!(second.unwrap().id, 1);
+        assert_eq!(last.unwrap().id, 1);
+        assert_eq!(next.unwrap().id, 2);
+        assert!(
+            tape.process_trade(&trade(5, 1, None))
+                .unwrap()
+                .workup
+                .is_none()
+        );
+    }
+}